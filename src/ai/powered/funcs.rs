@@ -97,6 +97,7 @@ impl<R: 'static> PoweredFunction for ConsumeGas<R> {
         func_complete!(gas_left)
     }
 
+    #[allow(unused_variables)]
     fn reset(self: &mut Self, parameter: &mut Self::World) {
         // No state.
     }
@@ -121,6 +122,7 @@ impl<R: 'static> PoweredFunction for ConsumeGasFail<R> {
         func_fail!(gas_left)
     }
 
+    #[allow(unused_variables)]
     fn reset(self: &mut Self, parameter: &mut Self::World) {
         // No state.
     }
@@ -137,6 +139,7 @@ mod tests {
         Reversed,
     }
 
+    #[allow(unreachable_patterns, unused_variables)]
     impl PoweredFunction for Example {
         type World = Vec<usize>;
         fn resume_with(
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::needless_return)]
+
 mod funcs;
 mod nodes;
 mod tree_def;
@@ -0,0 +1,324 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::{
+    component_types::ParameterizedSpriteAnimationSet,
+    data_types::ParameterizedSpriteAnimation,
+    hitboxes::{Hitbox, HitboxType, SpriteSheetHitboxes},
+    pivots::PackedFrame,
+};
+
+// Animation name used for sheets exported without any frame tags.
+pub const UNTAGGED_ANIMATION: &str = "Idle";
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct AsepriteRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct AsepriteSize {
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AsepriteFrame {
+    pub frame: AsepriteRect,
+    // Trimmed frames only hold the part of the canvas at sprite_source_size.
+    #[serde(default)]
+    pub trimmed: bool,
+    pub sprite_source_size: Option<AsepriteRect>,
+    pub source_size: AsepriteSize,
+    // Milliseconds.
+    pub duration: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    #[serde(default)]
+    pub direction: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsepriteSliceKey {
+    pub frame: usize,
+    pub bounds: AsepriteRect,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsepriteSlice {
+    pub name: String,
+    pub keys: Vec<AsepriteSliceKey>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AsepriteMeta {
+    pub image: String,
+    #[serde(default)]
+    pub frame_tags: Vec<AsepriteTag>,
+    #[serde(default)]
+    pub slices: Vec<AsepriteSlice>,
+}
+
+// Aseprite exports frames either as an array or as an object keyed by file name. Both keep the
+// export's frame order, which is also the atlas order.
+#[derive(Debug, Clone)]
+pub struct AsepriteFrames(pub Vec<AsepriteFrame>);
+
+impl<'de> Deserialize<'de> for AsepriteFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = AsepriteFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an array or map of frames")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut frames = vec![];
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(AsepriteFrames(frames))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = vec![];
+                while let Some((_name, frame)) = map.next_entry::<String, AsepriteFrame>()? {
+                    frames.push(frame);
+                }
+                Ok(AsepriteFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsepriteSheet {
+    pub frames: AsepriteFrames,
+    pub meta: AsepriteMeta,
+}
+
+impl AsepriteSheet {
+    pub fn frames(&self) -> &[AsepriteFrame] {
+        &self.frames.0
+    }
+
+    // A trimmed frame is pivoted on the center of its untrimmed canvas, so the sprite is drawn where
+    // it was on the canvas and slices, which are in canvas space, still line up with it.
+    pub fn packed_frames(&self) -> Vec<PackedFrame> {
+        self.frames()
+            .iter()
+            .map(|frame| {
+                let min = Vec2::new(frame.frame.x, frame.frame.y);
+                let pivot = frame
+                    .sprite_source_size
+                    .filter(|_| frame.trimmed)
+                    .map(|source| {
+                        Vec2::new(frame.source_size.w, frame.source_size.h) / 2.0
+                            - Vec2::new(source.x, source.y)
+                    });
+                PackedFrame {
+                    min,
+                    max: min + Vec2::new(frame.frame.w, frame.frame.h),
+                    pivot,
+                }
+            })
+            .collect()
+    }
+
+    fn tag_frames(&self, tag: &AsepriteTag) -> Vec<usize> {
+        let forward: Vec<usize> = (tag.from..=tag.to.min(self.frames().len() - 1)).collect();
+        let backward: Vec<usize> = forward.iter().rev().copied().collect();
+        let inner = |frames: &[usize]| {
+            frames
+                .get(1..frames.len().saturating_sub(1))
+                .unwrap_or(&[])
+                .to_vec()
+        };
+        match tag.direction.as_str() {
+            "reverse" => backward,
+            "pingpong" => [forward.clone(), inner(&backward)].concat(),
+            "pingpong_reverse" => [backward.clone(), inner(&forward)].concat(),
+            _ => forward,
+        }
+    }
+
+    fn build_animation(&self, frames: &[usize]) -> ParameterizedSpriteAnimation {
+        let duration = |frame_idx: usize| self.frames()[frame_idx].duration as f32 / 1000.0;
+        let mut animation = ParameterizedSpriteAnimation::new(0, 1, duration(frames[0]));
+        for (idx, frame_idx) in frames.iter().enumerate() {
+            animation.add_frame(*frame_idx);
+            animation.set_frame_duration(idx, duration(*frame_idx));
+        }
+        animation
+    }
+
+    // One animation per frame tag, or a single animation over every frame when nothing is tagged.
+    pub fn animation_set(&self) -> ParameterizedSpriteAnimationSet {
+        let mut animation_set = ParameterizedSpriteAnimationSet::default();
+        if self.frames().is_empty() {
+            return animation_set;
+        }
+        if self.meta.frame_tags.is_empty() {
+            let frames: Vec<usize> = (0..self.frames().len()).collect();
+            animation_set.add_new_animation(
+                UNTAGGED_ANIMATION.to_string(),
+                self.build_animation(&frames),
+            );
+        }
+        for tag in self.meta.frame_tags.iter() {
+            let frames = self.tag_frames(tag);
+            if !frames.is_empty() {
+                animation_set.add_new_animation(tag.name.clone(), self.build_animation(&frames));
+            }
+        }
+        animation_set
+    }
+
+    // Slices named Hit, Block, Hurt or UserN (anything after the type is ignored, so "Hit 2" works)
+    // become hitboxes. A slice key holds from its frame until the slice's next key, and empty
+    // bounds hide the slice.
+    pub fn hitboxes(&self) -> Option<SpriteSheetHitboxes> {
+        let mut hitboxes = SpriteSheetHitboxes::with_frames(self.frames().len());
+        let mut any = false;
+        for slice in self.meta.slices.iter() {
+            let hitbox_type = match slice_hitbox_type(&slice.name) {
+                Some(hitbox_type) => hitbox_type,
+                None => continue,
+            };
+            let mut keys: Vec<&AsepriteSliceKey> = slice.keys.iter().collect();
+            keys.sort_by_key(|key| key.frame);
+            for (key_idx, key) in keys.iter().enumerate() {
+                if key.bounds.w <= 0.0 || key.bounds.h <= 0.0 {
+                    continue;
+                }
+                let until = keys
+                    .get(key_idx + 1)
+                    .map_or(self.frames().len(), |next| next.frame);
+                for frame_idx in key.frame..until.min(self.frames().len()) {
+                    let half_size = Vec2::new(
+                        self.frames()[frame_idx].source_size.w,
+                        self.frames()[frame_idx].source_size.h,
+                    ) / 2.0;
+                    let min = Vec2::new(key.bounds.x, key.bounds.y) - half_size;
+                    let max = min + Vec2::new(key.bounds.w, key.bounds.h);
+                    hitboxes.add_hitbox(frame_idx, Hitbox::rect(hitbox_type, min, max));
+                    any = true;
+                }
+            }
+        }
+        if any {
+            Some(hitboxes)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn slice_hitbox_type(name: &str) -> Option<HitboxType> {
+    let kind = name.split_whitespace().next()?;
+    match kind {
+        "Hit" => Some(HitboxType::Hit),
+        "Block" => Some(HitboxType::Block),
+        "Hurt" => Some(HitboxType::Hurt),
+        _ => kind
+            .strip_prefix("User")
+            .and_then(|id| id.parse().ok())
+            .map(HitboxType::User),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r##"{
+        "frames": {
+            "Slime 0.aseprite": { "frame": {"x":0,"y":0,"w":32,"h":32}, "rotated": false, "trimmed": false,
+                "spriteSourceSize": {"x":0,"y":0,"w":32,"h":32}, "sourceSize": {"w":32,"h":32}, "duration": 100 },
+            "Slime 1.aseprite": { "frame": {"x":32,"y":0,"w":32,"h":32}, "rotated": false, "trimmed": false,
+                "spriteSourceSize": {"x":0,"y":0,"w":32,"h":32}, "sourceSize": {"w":32,"h":32}, "duration": 200 },
+            "Slime 2.aseprite": { "frame": {"x":64,"y":0,"w":32,"h":32}, "rotated": false, "trimmed": false,
+                "spriteSourceSize": {"x":0,"y":0,"w":32,"h":32}, "sourceSize": {"w":32,"h":32}, "duration": 100 }
+        },
+        "meta": {
+            "app": "http://www.aseprite.org/",
+            "image": "Slime.png",
+            "size": {"w":96,"h":32},
+            "frameTags": [
+                { "name": "Idle", "from": 0, "to": 0, "direction": "forward" },
+                { "name": "Lunge", "from": 0, "to": 2, "direction": "pingpong" }
+            ],
+            "slices": [
+                { "name": "Hit", "color": "#0000ffff", "keys": [
+                    { "frame": 1, "bounds": {"x":16,"y":8,"w":16,"h":8} },
+                    { "frame": 2, "bounds": {"x":0,"y":0,"w":0,"h":0} }
+                ] },
+                { "name": "Outline", "color": "#0000ffff", "keys": [
+                    { "frame": 0, "bounds": {"x":0,"y":0,"w":32,"h":32} }
+                ] }
+            ]
+        }
+    }"##;
+
+    #[test]
+    fn test_aseprite_sheet_import() {
+        let sheet: AsepriteSheet = serde_json::from_str(SHEET).unwrap();
+        assert_eq!(sheet.packed_frames()[2].min, Vec2::new(64.0, 0.0));
+
+        let animation_set = sheet.animation_set();
+        let lunge = animation_set.get_animation(&"Lunge".to_string()).unwrap();
+        assert_eq!(lunge.len(), 4);
+        assert_eq!(lunge.frame_duration(1), 0.2);
+        assert_eq!(lunge.get_frame_with_parameter(3, 0), Some(1));
+
+        let hitboxes = sheet.hitboxes().unwrap();
+        assert!(hitboxes.get_hitboxes(0).unwrap().is_empty());
+        assert_eq!(
+            hitboxes.get_hitboxes(1).unwrap(),
+            &vec![Hitbox::rect(
+                HitboxType::Hit,
+                Vec2::new(0.0, -8.0),
+                Vec2::new(16.0, 0.0)
+            )]
+        );
+        assert!(hitboxes.get_hitboxes(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trimmed_frame_pivots_on_its_canvas_center() {
+        let sheet: AsepriteSheet = serde_json::from_str(
+            r#"{
+                "frames": [
+                    { "frame": {"x":0,"y":0,"w":12,"h":20}, "rotated": false, "trimmed": true,
+                        "spriteSourceSize": {"x":4,"y":10,"w":12,"h":20}, "sourceSize": {"w":32,"h":32},
+                        "duration": 100 }
+                ],
+                "meta": { "image": "Slime.png" }
+            }"#,
+        )
+        .unwrap();
+        let frame = &sheet.packed_frames()[0];
+        assert_eq!(frame.pivot(), Vec2::new(12.0, 6.0));
+        assert_eq!(frame.center_offset(), Vec2::new(-6.0, 4.0));
+    }
+}
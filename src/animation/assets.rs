@@ -1,12 +1,24 @@
+use std::path::PathBuf;
+
+#[allow(unused_imports)]
 use bevy::{
-    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    asset::{AssetLoader, AssetPath, BoxedFuture, HandleId, LoadContext, LoadedAsset},
+    ecs::system::EntityCommands,
     prelude::*,
     reflect::TypeUuid,
     render::texture::{FileTextureError, ImageType},
 };
 use serde::{Deserialize, Serialize};
 
-use super::{component_types::ParameterizedSpriteAnimationSet, hitboxes::SpriteSheetHitboxes};
+use super::{
+    aseprite::AsepriteSheet,
+    component_types::ParameterizedSpriteAnimationSet,
+    hitboxes::SpriteSheetHitboxes,
+    pivots::{PackedFrame, SpritePivots},
+    validation::{
+        atlas_frames, ensure_valid, validate_animation_set, validate_hitboxes, validate_sprite_def,
+    },
+};
 
 #[derive(Default, Debug, TypeUuid)]
 #[uuid = "d5ecf4c3-6d03-49db-a6ad-1e4cd5c2369c"]
@@ -14,14 +26,26 @@ pub struct SpriteAnimationAsset {
     pub texture_atlas: Handle<TextureAtlas>,
     pub parameterized_animation_set: ParameterizedSpriteAnimationSet,
     pub hitboxes: Option<SpriteSheetHitboxes>,
+    pub pivots: Option<SpritePivots>,
+    // Files read while loading besides the sprite file itself. Edits to them are copied into the
+    // sprite by sprite_animation_dependency_reload_system.
+    pub atlas_path: PathBuf,
+    pub animation_path: Option<PathBuf>,
 }
 
+// The atlas is either a uniform grid of atlas_tile_size tiles or, when packed_frames is given, one
+// explicit rect per frame. Hitboxes of packed sheets are relative to each frame's pivot.
 #[derive(Debug, Serialize, Deserialize, TypeUuid)]
 #[uuid = "33599347-16e6-4170-be40-a12e5a64253a"]
 pub struct SpriteAnimationAssetDef {
+    #[serde(default)]
     pub atlas_tile_size: Vec2,
+    #[serde(default)]
     pub atlas_columns: usize,
+    #[serde(default)]
     pub atlas_rows: usize,
+    #[serde(default)]
+    pub packed_frames: Vec<PackedFrame>,
     pub atlas_path: String,
     pub animation_path: String,
     pub hitboxes: Option<SpriteSheetHitboxes>,
@@ -38,27 +62,56 @@ impl AssetLoader for SpriteAnimationAssetLoader {
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
             let asset_def = ron::de::from_bytes::<SpriteAnimationAssetDef>(bytes)?;
-            let texture_bytes = load_context.read_asset_bytes(asset_def.atlas_path).await?;
+            ensure_valid(validate_sprite_def(&asset_def))?;
+            let atlas_path = PathBuf::from(&asset_def.atlas_path);
+            let animation_path = PathBuf::from(&asset_def.animation_path);
+            let texture_bytes = load_context.read_asset_bytes(&atlas_path).await?;
             let image = Image::from_buffer(&texture_bytes, ImageType::Extension("png"))?;
+            let image_size = image.texture_descriptor.size;
             let texture = load_context.set_labeled_asset("texture", LoadedAsset::new(image));
-            let texture_atlas_real = TextureAtlas::from_grid(
-                texture,
-                asset_def.atlas_tile_size,
-                asset_def.atlas_columns,
-                asset_def.atlas_rows,
-            );
+            let (texture_atlas_real, pivots) = if asset_def.packed_frames.is_empty() {
+                let texture_atlas = TextureAtlas::from_grid(
+                    texture,
+                    asset_def.atlas_tile_size,
+                    asset_def.atlas_columns,
+                    asset_def.atlas_rows,
+                );
+                (texture_atlas, None)
+            } else {
+                let mut texture_atlas = TextureAtlas::new_empty(
+                    texture,
+                    Vec2::new(image_size.width as f32, image_size.height as f32),
+                );
+                for frame in asset_def.packed_frames.iter() {
+                    texture_atlas.add_texture(frame.rect());
+                }
+                let pivots = SpritePivots::from_frames(&asset_def.packed_frames);
+                (texture_atlas, Some(pivots))
+            };
             let texture_atlas =
                 load_context.set_labeled_asset("atlas", LoadedAsset::new(texture_atlas_real));
-            let animation_bytes = load_context
-                .read_asset_bytes(asset_def.animation_path.clone())
-                .await?;
+            let animation_bytes = load_context.read_asset_bytes(&animation_path).await?;
             let parameterized_animation_set =
                 ron::de::from_bytes::<ParameterizedSpriteAnimationSet>(&animation_bytes)?;
-            load_context.set_default_asset(LoadedAsset::new(SpriteAnimationAsset {
+            let frames = atlas_frames(&asset_def);
+            let mut problems = validate_animation_set(&parameterized_animation_set, frames);
+            if let Some(hitboxes) = &asset_def.hitboxes {
+                problems.extend(validate_hitboxes(hitboxes, frames));
+            }
+            ensure_valid(problems)?;
+            let sprite_animation = SpriteAnimationAsset {
                 texture_atlas,
                 parameterized_animation_set,
                 hitboxes: asset_def.hitboxes,
-            }));
+                pivots,
+                atlas_path: atlas_path.clone(),
+                animation_path: Some(animation_path.clone()),
+            };
+            load_context.set_default_asset(
+                LoadedAsset::new(sprite_animation)
+                    .with_dependency(AssetPath::new(atlas_path, None))
+                    .with_dependency(AssetPath::new(animation_path, None)),
+            );
             Ok(())
         })
     }
@@ -68,6 +121,114 @@ impl AssetLoader for SpriteAnimationAssetLoader {
     }
 }
 
+// Aseprite's JSON sheet export, saved as .aseprite.json so other JSON assets keep their own
+// loaders. The image is looked up next to the JSON file, the same way Aseprite writes it.
+#[derive(Default)]
+pub struct AsepriteSheetLoader;
+
+impl AssetLoader for AsepriteSheetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move { load_aseprite_sheet(bytes, load_context).await })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
+    }
+}
+
+async fn load_aseprite_sheet(
+    bytes: &[u8],
+    load_context: &mut LoadContext<'_>,
+) -> Result<(), anyhow::Error> {
+    let sheet = serde_json::from_slice::<AsepriteSheet>(bytes)?;
+    let image_path = load_context.path().parent().map_or_else(
+        || PathBuf::from(&sheet.meta.image),
+        |parent| parent.join(&sheet.meta.image),
+    );
+    let image_extension = image_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("png")
+        .to_string();
+    let texture_bytes = load_context.read_asset_bytes(&image_path).await?;
+    let image = Image::from_buffer(&texture_bytes, ImageType::Extension(&image_extension))?;
+    let image_size = image.texture_descriptor.size;
+    let texture = load_context.set_labeled_asset("texture", LoadedAsset::new(image));
+    let mut texture_atlas_real = TextureAtlas::new_empty(
+        texture,
+        Vec2::new(image_size.width as f32, image_size.height as f32),
+    );
+    let packed_frames = sheet.packed_frames();
+    for frame in packed_frames.iter() {
+        texture_atlas_real.add_texture(frame.rect());
+    }
+    let texture_atlas =
+        load_context.set_labeled_asset("atlas", LoadedAsset::new(texture_atlas_real));
+    let parameterized_animation_set = sheet.animation_set();
+    let hitboxes = sheet.hitboxes();
+    let mut problems = validate_animation_set(&parameterized_animation_set, sheet.frames().len());
+    if let Some(hitboxes) = &hitboxes {
+        problems.extend(validate_hitboxes(hitboxes, sheet.frames().len()));
+    }
+    ensure_valid(problems)?;
+    let sprite_animation = SpriteAnimationAsset {
+        texture_atlas,
+        parameterized_animation_set,
+        hitboxes,
+        pivots: Some(SpritePivots::from_frames(&packed_frames)),
+        atlas_path: image_path.clone(),
+        animation_path: None,
+    };
+    load_context.set_default_asset(
+        LoadedAsset::new(sprite_animation).with_dependency(AssetPath::new(image_path, None)),
+    );
+    Ok(())
+}
+
+// Lets .anim files load on their own, which is how the asset server reloads a sprite's dependency
+// after an edit.
+#[derive(Default)]
+pub struct ParameterizedSpriteAnimationSetLoader;
+
+impl AssetLoader for ParameterizedSpriteAnimationSetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let animation_set = ron::de::from_bytes::<ParameterizedSpriteAnimationSet>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(animation_set));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim"]
+    }
+}
+
+fn insert_sprite_animation(
+    entity_commands: &mut EntityCommands,
+    sprite_animation: &SpriteAnimationAsset,
+) {
+    entity_commands
+        .insert(sprite_animation.texture_atlas.clone())
+        .insert(sprite_animation.parameterized_animation_set.clone());
+    match &sprite_animation.hitboxes {
+        Some(hitboxes) => entity_commands.insert(hitboxes.clone()),
+        None => entity_commands.remove::<SpriteSheetHitboxes>(),
+    };
+    match &sprite_animation.pivots {
+        Some(pivots) => entity_commands.insert(pivots.clone()),
+        None => entity_commands.remove::<SpritePivots>(),
+    };
+}
+
 pub fn sprite_animation_unpack_system(
     query: Query<(Entity, &Handle<SpriteAnimationAsset>), Without<Handle<TextureAtlas>>>,
     sprite_animation_assets: Res<Assets<SpriteAnimationAsset>>,
@@ -75,12 +236,104 @@ pub fn sprite_animation_unpack_system(
 ) {
     for (entity, sprite_animation) in query.iter() {
         if let Some(sprite_animation) = sprite_animation_assets.get(sprite_animation) {
-            commands
-                .entity(entity)
-                .insert(sprite_animation.texture_atlas.clone())
-                .insert(sprite_animation.parameterized_animation_set.clone());
-            if let Some(hitboxes) = &sprite_animation.hitboxes {
-                commands.entity(entity).insert(hitboxes.clone());
+            insert_sprite_animation(&mut commands.entity(entity), sprite_animation);
+        }
+    }
+}
+
+// Entities that already unpacked a sprite pick up its new atlas, animations and hitboxes when it
+// is reloaded.
+pub fn sprite_animation_reload_system(
+    mut asset_events: EventReader<AssetEvent<SpriteAnimationAsset>>,
+    query: Query<(Entity, &Handle<SpriteAnimationAsset>), With<Handle<TextureAtlas>>>,
+    sprite_animation_assets: Res<Assets<SpriteAnimationAsset>>,
+    mut commands: Commands,
+) {
+    for asset_event in asset_events.iter() {
+        let handle = match asset_event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        let sprite_animation = match sprite_animation_assets.get(handle) {
+            Some(sprite_animation) => sprite_animation,
+            None => continue,
+        };
+        for (entity, entity_handle) in query.iter() {
+            if entity_handle == handle {
+                insert_sprite_animation(&mut commands.entity(entity), sprite_animation);
+            }
+        }
+    }
+}
+
+// The asset server reloads a changed dependency as an asset of its own and has no way to reload
+// the sprites that read it, so the new image or animation set is copied into those sprites here.
+pub fn sprite_animation_dependency_reload_system(
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut animation_set_events: EventReader<AssetEvent<ParameterizedSpriteAnimationSet>>,
+    asset_server: Res<AssetServer>,
+    animation_sets: Res<Assets<ParameterizedSpriteAnimationSet>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut images: ResMut<Assets<Image>>,
+    mut sprite_animation_assets: ResMut<Assets<SpriteAnimationAsset>>,
+) {
+    let modified_path = |handle_id: HandleId| {
+        asset_server
+            .get_handle_path(handle_id)
+            .map(|asset_path| (asset_path.path().to_path_buf(), handle_id))
+    };
+    let modified_images: Vec<(PathBuf, HandleId)> = image_events
+        .iter()
+        .filter_map(|asset_event| match asset_event {
+            AssetEvent::Modified { handle } => modified_path(handle.id),
+            _ => None,
+        })
+        .collect();
+    let modified_animation_sets: Vec<(PathBuf, HandleId)> = animation_set_events
+        .iter()
+        .filter_map(|asset_event| match asset_event {
+            AssetEvent::Modified { handle } => modified_path(handle.id),
+            _ => None,
+        })
+        .collect();
+    if modified_images.is_empty() && modified_animation_sets.is_empty() {
+        return;
+    }
+    let sprite_ids: Vec<HandleId> = sprite_animation_assets.ids().collect();
+    for sprite_id in sprite_ids {
+        let sprite_animation = match sprite_animation_assets.get(sprite_id) {
+            Some(sprite_animation) => sprite_animation,
+            None => continue,
+        };
+        let image = modified_images
+            .iter()
+            .find(|(path, _image_id)| *path == sprite_animation.atlas_path)
+            .and_then(|(_path, image_id)| images.get(*image_id).cloned());
+        let texture = texture_atlases
+            .get(&sprite_animation.texture_atlas)
+            .map(|texture_atlas| texture_atlas.texture.clone());
+        if let (Some(image), Some(texture)) = (image, texture) {
+            images.set_untracked(texture, image);
+        }
+        let animation_set = modified_animation_sets
+            .iter()
+            .find(|(path, _set_id)| Some(path) == sprite_animation.animation_path.as_ref())
+            .and_then(|(_path, set_id)| animation_sets.get(*set_id));
+        if let Some(animation_set) = animation_set {
+            let frames = texture_atlases
+                .get(&sprite_animation.texture_atlas)
+                .map_or(0, |texture_atlas| texture_atlas.textures.len());
+            if let Err(err) = ensure_valid(validate_animation_set(animation_set, frames)) {
+                warn!(
+                    "Not reloading {:?}: {}",
+                    sprite_animation.animation_path, err
+                );
+                continue;
+            }
+            // Going through get_mut sends the Modified event sprite_animation_reload_system
+            // listens for.
+            if let Some(sprite_animation) = sprite_animation_assets.get_mut(sprite_id) {
+                sprite_animation.parameterized_animation_set = animation_set.clone();
             }
         }
     }
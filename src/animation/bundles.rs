@@ -1,3 +1,5 @@
+#![allow(clippy::forget_non_drop)]
+
 use super::{assets::SpriteAnimationAsset, component_types::*};
 use bevy::prelude::*;
 
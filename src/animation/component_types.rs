@@ -11,7 +11,20 @@ use super::data_types::*;
 pub struct AnimationState {
     animation: String,
     pub timer: Timer,
-    pub looping: bool,
+    pub mode: PlaybackMode,
+    // Playback speed multiplier, kept across transitions.
+    pub speed: f32,
+    // Frames already entered, as counted by ParameterizedSpriteAnimation::frames_elapsed.
+    #[reflect(ignore)]
+    pub sampled_frames: Option<usize>,
+}
+
+// Sent once each time an entity's animation enters a frame carrying a named event.
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub animation: String,
+    pub name: String,
 }
 
 impl Default for AnimationState {
@@ -29,29 +42,36 @@ impl AnimationState {
         Self::new(animation, false)
     }
 
-    pub fn new(animation: String, looping: bool) -> Self {
+    pub fn new(animation: String, mode: impl Into<PlaybackMode>) -> Self {
         AnimationState {
             animation,
             timer: Timer::from_seconds(3600.0, true),
-            looping,
+            mode: mode.into(),
+            speed: 1.0,
+            sampled_frames: None,
         }
     }
 
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
     pub fn get_animation(&self) -> &String {
         &self.animation
     }
 
-    pub fn transition_to(&mut self, animation: &str, looping: bool) {
+    pub fn transition_to(&mut self, animation: &str, mode: impl Into<PlaybackMode>) {
         if self.animation != animation {
-            *self = Self::new(animation.to_string(), looping);
+            *self = Self::new(animation.to_string(), mode).with_speed(self.speed);
         }
     }
 
-    pub fn try_transition_to(&mut self, animation: &str, looping: bool) -> bool {
+    pub fn try_transition_to(&mut self, animation: &str, mode: impl Into<PlaybackMode>) -> bool {
         if self.get_animation().eq(animation) {
             false
         } else {
-            self.transition_to(animation, looping);
+            self.transition_to(animation, mode);
             true
         }
     }
@@ -63,6 +83,11 @@ pub struct ParameterizedSpriteAnimationSet {
     animations: HashMap<String, ParameterizedSpriteAnimation>,
 }
 
+#[allow(
+    clippy::len_without_is_empty,
+    clippy::useless_conversion,
+    clippy::unnecessary_map_or
+)]
 impl ParameterizedSpriteAnimationSet {
     pub fn len(&self) -> usize {
         self.animations.len()
@@ -89,13 +114,8 @@ impl ParameterizedSpriteAnimationSet {
 
     pub fn animation_complete(&self, animation_state: &AnimationState) -> bool {
         self.get_animation(animation_state.get_animation())
-            .and_then(|animation| {
-                animation.sample_with_parameter(
-                    0,
-                    animation_state.timer.elapsed_secs(),
-                    animation_state.looping,
-                )
+            .map_or(true, |animation| {
+                animation.is_complete(animation_state.timer.elapsed_secs(), animation_state.mode)
             })
-            .is_none()
     }
 }
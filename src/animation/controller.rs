@@ -0,0 +1,230 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    component_types::{AnimationState, ParameterizedSpriteAnimationSet},
+    data_types::PlaybackMode,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ControllerState {
+    pub animation: String,
+    pub mode: PlaybackMode,
+    // State to move to once a one-shot animation has finished.
+    #[serde(default)]
+    pub on_complete: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum TransitionCondition {
+    Flag(String),
+    NotFlag(String),
+    Above(String, f32),
+    Below(String, f32),
+    // Holds for the one frame a trigger was pulled.
+    Trigger(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ControllerTransition {
+    // States this transition leaves from. Empty means any state.
+    #[serde(default)]
+    pub from: Vec<String>,
+    pub to: String,
+    pub conditions: Vec<TransitionCondition>,
+}
+
+// A state graph over the animations of one sprite sheet. Transitions are checked in order and the
+// first whose conditions all hold wins, so list triggers before the states they interrupt.
+#[derive(Debug, Serialize, Deserialize, Clone, TypeUuid)]
+#[uuid = "0f3b8b44-7f39-4c7e-9a51-5a2d6f0d8c19"]
+pub struct AnimationController {
+    pub initial: String,
+    pub states: HashMap<String, ControllerState>,
+    pub transitions: Vec<ControllerTransition>,
+}
+
+impl AnimationController {
+    pub fn get_state(&self, name: &str) -> Option<&ControllerState> {
+        self.states.get(name)
+    }
+
+    pub fn next_state(&self, animator: &Animator, complete: bool) -> Option<&str> {
+        let current = match animator.current_state() {
+            Some(current) if self.states.contains_key(current) => current,
+            _ => return Some(self.initial.as_str()),
+        };
+        self.transitions
+            .iter()
+            .find(|transition| {
+                transition.to != current
+                    && (transition.from.is_empty()
+                        || transition.from.iter().any(|from| from == current))
+                    && transition
+                        .conditions
+                        .iter()
+                        .all(|condition| animator.holds(condition))
+            })
+            .map(|transition| transition.to.as_str())
+            .or_else(|| {
+                self.states[current]
+                    .on_complete
+                    .as_deref()
+                    .filter(|_next| complete)
+            })
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationControllerLoader;
+
+impl AssetLoader for AnimationControllerLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let controller = ron::de::from_bytes::<AnimationController>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(controller));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["controller"]
+    }
+}
+
+// Drives AnimationState from an AnimationController. Gameplay sets values, flags and triggers here
+// and the controller picks the animation.
+#[derive(Component, Debug, Default)]
+pub struct Animator {
+    pub controller: Handle<AnimationController>,
+    // Leaves AnimationState alone, for animations played from outside the graph such as death.
+    pub paused: bool,
+    state: Option<String>,
+    values: HashMap<String, f32>,
+    flags: HashSet<String>,
+    triggers: HashSet<String>,
+}
+
+impl Animator {
+    pub fn new(controller: Handle<AnimationController>) -> Self {
+        Animator {
+            controller,
+            ..Default::default()
+        }
+    }
+
+    pub fn current_state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    pub fn set_value(&mut self, name: &str, value: f32) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn value(&self, name: &str) -> f32 {
+        self.values.get(name).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_flag(&mut self, name: &str, set: bool) {
+        if set {
+            self.flags.insert(name.to_string());
+        } else {
+            self.flags.remove(name);
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    // Triggers last until the controller next runs, whether or not a transition used them.
+    pub fn trigger(&mut self, name: &str) {
+        self.triggers.insert(name.to_string());
+    }
+
+    pub fn holds(&self, condition: &TransitionCondition) -> bool {
+        match condition {
+            TransitionCondition::Flag(name) => self.flag(name),
+            TransitionCondition::NotFlag(name) => !self.flag(name),
+            TransitionCondition::Above(name, threshold) => self.value(name) > *threshold,
+            TransitionCondition::Below(name, threshold) => self.value(name) < *threshold,
+            TransitionCondition::Trigger(name) => self.triggers.contains(name),
+        }
+    }
+}
+
+pub fn animation_controller_system(
+    controllers: Res<Assets<AnimationController>>,
+    mut query: Query<(
+        &mut Animator,
+        &mut AnimationState,
+        Option<&ParameterizedSpriteAnimationSet>,
+    )>,
+) {
+    for (mut animator, mut animation_state, animation_set) in query.iter_mut() {
+        if animator.paused {
+            continue;
+        }
+        if let Some(controller) = controllers.get(&animator.controller) {
+            let complete = animation_set
+                .is_some_and(|animation_set| animation_set.animation_complete(&animation_state));
+            if let Some(next) = controller.next_state(&animator, complete) {
+                if let Some(state) = controller.get_state(next) {
+                    *animation_state = AnimationState::new(state.animation.clone(), state.mode)
+                        .with_speed(animation_state.speed);
+                    animator.state = Some(next.to_string());
+                }
+            }
+        }
+        animator.triggers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTROLLER: &str = r#"(
+        initial: "Idle",
+        states: {
+            "Idle": (animation: "Idle", mode: Loop),
+            "Walk": (animation: "Walk", mode: Loop),
+            "Lunge": (animation: "Lunge", mode: Once, on_complete: Some("Idle")),
+        },
+        transitions: [
+            (to: "Lunge", conditions: [Trigger("lunge")]),
+            (from: ["Idle"], to: "Walk", conditions: [Flag("grounded"), Above("speed", 0.0)]),
+        ],
+    )"#;
+
+    #[test]
+    fn test_controller_transitions() {
+        let controller: AnimationController = ron::de::from_str(CONTROLLER).unwrap();
+        let mut animator = Animator::default();
+        assert_eq!(controller.next_state(&animator, false), Some("Idle"));
+
+        animator.state = Some("Idle".to_string());
+        animator.set_value("speed", 3.0);
+        assert_eq!(controller.next_state(&animator, false), None);
+        animator.set_flag("grounded", true);
+        assert_eq!(controller.next_state(&animator, false), Some("Walk"));
+
+        animator.trigger("lunge");
+        assert_eq!(controller.next_state(&animator, false), Some("Lunge"));
+
+        animator.state = Some("Lunge".to_string());
+        animator.triggers.clear();
+        assert_eq!(controller.next_state(&animator, false), None);
+        assert_eq!(controller.next_state(&animator, true), Some("Idle"));
+    }
+}
@@ -1,13 +1,56 @@
+use bevy::reflect::Reflect;
 use serde::{Deserialize, Serialize};
 
+// A named gameplay hook fired when playback enters the given frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameEvent {
+    pub frame: usize,
+    pub name: String,
+}
+
+// How playback moves through the frames. Once and Reverse end after a single pass, HoldLast keeps
+// showing the final frame, Loop and PingPong repeat forever.
+#[derive(Serialize, Deserialize, Debug, Reflect, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackMode {
+    Once,
+    #[default]
+    Loop,
+    PingPong,
+    Reverse,
+    HoldLast,
+}
+
+impl From<bool> for PlaybackMode {
+    fn from(looping: bool) -> Self {
+        if looping {
+            PlaybackMode::Loop
+        } else {
+            PlaybackMode::Once
+        }
+    }
+}
+
+impl PlaybackMode {
+    pub fn repeats(&self) -> bool {
+        matches!(self, PlaybackMode::Loop | PlaybackMode::PingPong)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ParameterizedSpriteAnimation {
     base_frames: Vec<usize>,
     pub parameters: usize,
     pub parameter_offset: usize,
     pub single_frame_duration: f32,
+    // Overrides single_frame_duration for the first few frames. Frames past the end of the list
+    // keep the single duration.
+    #[serde(default)]
+    pub frame_durations: Vec<f32>,
+    #[serde(default)]
+    pub events: Vec<FrameEvent>,
 }
 
+#[allow(clippy::len_without_is_empty)]
 impl ParameterizedSpriteAnimation {
     pub fn new(parameter_offset: usize, parameters: usize, single_frame_duration: f32) -> Self {
         ParameterizedSpriteAnimation {
@@ -15,6 +58,8 @@ impl ParameterizedSpriteAnimation {
             parameters,
             parameter_offset,
             single_frame_duration,
+            frame_durations: vec![],
+            events: vec![],
         }
     }
 
@@ -36,29 +81,282 @@ impl ParameterizedSpriteAnimation {
         }
     }
 
+    // Parameters past the last row play the last row.
+    pub fn clamp_parameter(&self, parameter: usize) -> usize {
+        parameter.min(self.parameters.saturating_sub(1))
+    }
+
     pub fn set_frame(&mut self, frame_idx: usize, sprite_idx: usize) {
         self.base_frames[frame_idx] = sprite_idx;
     }
 
     pub fn remove_frame(&mut self, frame_idx: usize) {
         self.base_frames.remove(frame_idx);
+        if frame_idx < self.frame_durations.len() {
+            self.frame_durations.remove(frame_idx);
+        }
+        // Events go with their frame, later ones move down with theirs.
+        self.events.retain(|event| event.frame != frame_idx);
+        for event in self.events.iter_mut() {
+            if event.frame > frame_idx {
+                event.frame -= 1;
+            }
+        }
+    }
+
+    pub fn frame_duration(&self, frame_idx: usize) -> f32 {
+        self.frame_durations
+            .get(frame_idx)
+            .copied()
+            .unwrap_or(self.single_frame_duration)
+    }
+
+    pub fn set_frame_duration(&mut self, frame_idx: usize, duration: f32) {
+        if self.frame_durations.len() <= frame_idx {
+            self.frame_durations
+                .resize(frame_idx + 1, self.single_frame_duration);
+        }
+        self.frame_durations[frame_idx] = duration;
+    }
+
+    // Number of frames shown in one pass. A ping-pong pass doesn't repeat its end frames.
+    fn steps_per_pass(&self, mode: PlaybackMode) -> usize {
+        match mode {
+            PlaybackMode::PingPong if self.base_frames.len() > 1 => 2 * self.base_frames.len() - 2,
+            _ => self.base_frames.len(),
+        }
+    }
+
+    fn frame_at_step(&self, step: usize, mode: PlaybackMode) -> usize {
+        let last = self.base_frames.len() - 1;
+        match mode {
+            PlaybackMode::Reverse => last - step,
+            PlaybackMode::PingPong if step > last => 2 * last - step,
+            _ => step,
+        }
+    }
+
+    pub fn pass_duration(&self, mode: PlaybackMode) -> f32 {
+        (0..self.steps_per_pass(mode))
+            .map(|step| self.frame_duration(self.frame_at_step(step, mode)))
+            .sum()
+    }
+
+    // Frames entered so far, counting every repeat, and the frame showing now. None once a
+    // one-shot animation has ended.
+    fn playback_position(&self, time_index: f32, mode: PlaybackMode) -> Option<(usize, usize)> {
+        let steps = self.steps_per_pass(mode);
+        let pass_duration = self.pass_duration(mode);
+        if steps == 0 || pass_duration <= 0.0 {
+            return None;
+        }
+        let mut passes = 0;
+        let mut time_index = time_index;
+        if time_index >= pass_duration {
+            match mode {
+                PlaybackMode::Once | PlaybackMode::Reverse => return None,
+                PlaybackMode::HoldLast => {
+                    return Some((steps - 1, self.frame_at_step(steps - 1, mode)))
+                }
+                PlaybackMode::Loop | PlaybackMode::PingPong => {
+                    passes = (time_index / pass_duration) as usize;
+                    time_index %= pass_duration;
+                }
+            }
+        }
+        let mut step = 0;
+        while step + 1 < steps {
+            let duration = self.frame_duration(self.frame_at_step(step, mode));
+            if time_index < duration {
+                break;
+            }
+            time_index -= duration;
+            step += 1;
+        }
+        Some((passes * steps + step, self.frame_at_step(step, mode)))
     }
 
     pub fn sample_with_parameter(
         &self,
         parameter: usize,
-        mut time_index: f32,
-        looping: bool,
+        time_index: f32,
+        mode: PlaybackMode,
     ) -> Option<usize> {
-        let animation_duration = self.base_frames.len() as f32 * self.single_frame_duration;
-        if time_index >= animation_duration {
-            if !looping {
-                return None;
-            } else {
-                time_index = time_index % animation_duration;
-            }
+        self.playback_position(time_index, mode)
+            .and_then(|(_steps, frame_idx)| self.get_frame_with_parameter(frame_idx, parameter))
+    }
+
+    pub fn is_complete(&self, time_index: f32, mode: PlaybackMode) -> bool {
+        if self.base_frames.is_empty() {
+            true
+        } else {
+            !mode.repeats() && time_index >= self.pass_duration(mode)
+        }
+    }
+
+    // Frames entered so far, counting every repeat, or None once a one-shot animation has ended.
+    pub fn frames_elapsed(&self, time_index: f32, mode: PlaybackMode) -> Option<usize> {
+        self.playback_position(time_index, mode)
+            .map(|(steps, _frame_idx)| steps)
+    }
+
+    pub fn add_event(&mut self, frame_idx: usize, name: &str) {
+        self.events.push(FrameEvent {
+            frame: frame_idx,
+            name: name.to_string(),
+        });
+    }
+
+    pub fn events_on_frame(&self, frame_idx: usize) -> impl Iterator<Item = &str> {
+        self.events
+            .iter()
+            .filter(move |event| event.frame == frame_idx)
+            .map(|event| event.name.as_str())
+    }
+
+    // Events for every frame entered after `from` up to and including `to`, both counted by
+    // frames_elapsed so a repeat replays the early frames. With no `from` the first frame counts
+    // as entered too.
+    pub fn events_crossed(&self, from: Option<usize>, to: usize, mode: PlaybackMode) -> Vec<&str> {
+        let steps = self.steps_per_pass(mode);
+        let start = from.map_or(0, |from| from + 1);
+        (start..=to)
+            .flat_map(|frames| self.events_on_frame(self.frame_at_step(frames % steps, mode)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_fire_once_per_crossing_and_on_wrap() {
+        let mut animation = ParameterizedSpriteAnimation::new(0, 1, 0.1);
+        animation.add_frame(0);
+        animation.add_frame(1);
+        animation.add_frame(2);
+        animation.add_event(0, "footstep");
+        animation.add_event(2, "damage_on");
+
+        let looping = PlaybackMode::Loop;
+        assert_eq!(animation.events_crossed(None, 0, looping), vec!["footstep"]);
+        assert!(animation.events_crossed(Some(0), 1, looping).is_empty());
+        assert_eq!(
+            animation.events_crossed(Some(1), 3, looping),
+            vec!["damage_on", "footstep"]
+        );
+        assert_eq!(animation.frames_elapsed(0.35, looping), Some(3));
+        assert_eq!(animation.frames_elapsed(0.35, PlaybackMode::Once), None);
+    }
+
+    #[test]
+    fn test_remove_frame_keeps_events_on_their_frames() {
+        let mut animation = ParameterizedSpriteAnimation::new(0, 1, 0.1);
+        for sprite_idx in [0, 1, 2] {
+            animation.add_frame(sprite_idx);
         }
-        let frame_idx = (time_index / self.single_frame_duration) as usize;
-        self.get_frame_with_parameter(frame_idx, parameter)
+        animation.add_event(0, "footstep");
+        animation.add_event(1, "windup");
+        animation.add_event(2, "damage_on");
+
+        animation.remove_frame(1);
+        assert_eq!(
+            animation.events_on_frame(0).collect::<Vec<_>>(),
+            ["footstep"]
+        );
+        assert_eq!(
+            animation.events_on_frame(1).collect::<Vec<_>>(),
+            ["damage_on"]
+        );
+        assert_eq!(animation.events.len(), 2);
+    }
+
+    fn four_frame_animation() -> ParameterizedSpriteAnimation {
+        let mut animation = ParameterizedSpriteAnimation::new(4, 2, 0.25);
+        for sprite_idx in [10, 11, 12, 13] {
+            animation.add_frame(sprite_idx);
+        }
+        animation
+    }
+
+    #[test]
+    fn test_sample_once() {
+        let animation = four_frame_animation();
+        let mode = PlaybackMode::Once;
+        assert_eq!(animation.sample_with_parameter(0, 0.0, mode), Some(10));
+        assert_eq!(animation.sample_with_parameter(0, 0.8, mode), Some(13));
+        assert_eq!(animation.sample_with_parameter(1, 0.3, mode), Some(15));
+        assert_eq!(animation.sample_with_parameter(0, 1.0, mode), None);
+        assert_eq!(animation.sample_with_parameter(2, 0.0, mode), None);
+        assert!(animation.is_complete(1.0, mode));
+    }
+
+    #[test]
+    fn test_sample_loop() {
+        let animation = four_frame_animation();
+        let mode = PlaybackMode::Loop;
+        assert_eq!(animation.sample_with_parameter(0, 0.8, mode), Some(13));
+        assert_eq!(animation.sample_with_parameter(0, 1.1, mode), Some(10));
+        assert_eq!(animation.sample_with_parameter(0, 2.6, mode), Some(12));
+        assert!(!animation.is_complete(10.0, mode));
+    }
+
+    #[test]
+    fn test_sample_ping_pong() {
+        let animation = four_frame_animation();
+        let mode = PlaybackMode::PingPong;
+        let frames: Vec<_> = (0..8)
+            .map(|step| {
+                animation
+                    .sample_with_parameter(0, step as f32 * 0.25 + 0.1, mode)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(frames, vec![10, 11, 12, 13, 12, 11, 10, 11]);
+        assert!(!animation.is_complete(10.0, mode));
+    }
+
+    #[test]
+    fn test_sample_reverse() {
+        let animation = four_frame_animation();
+        let mode = PlaybackMode::Reverse;
+        assert_eq!(animation.sample_with_parameter(0, 0.1, mode), Some(13));
+        assert_eq!(animation.sample_with_parameter(0, 0.8, mode), Some(10));
+        assert_eq!(animation.sample_with_parameter(0, 1.0, mode), None);
+        assert!(animation.is_complete(1.0, mode));
+    }
+
+    #[test]
+    fn test_sample_hold_last() {
+        let animation = four_frame_animation();
+        let mode = PlaybackMode::HoldLast;
+        assert_eq!(animation.sample_with_parameter(0, 0.6, mode), Some(12));
+        assert_eq!(animation.sample_with_parameter(0, 5.0, mode), Some(13));
+        assert!(!animation.is_complete(0.9, mode));
+        assert!(animation.is_complete(1.0, mode));
+        assert_eq!(animation.frames_elapsed(5.0, mode), Some(3));
+    }
+
+    #[test]
+    fn test_sample_per_frame_durations() {
+        let mut animation = four_frame_animation();
+        animation.set_frame_duration(1, 0.5);
+        assert_eq!(animation.pass_duration(PlaybackMode::Loop), 1.25);
+        let mode = PlaybackMode::Once;
+        assert_eq!(animation.sample_with_parameter(0, 0.25, mode), Some(11));
+        assert_eq!(animation.sample_with_parameter(0, 0.7, mode), Some(11));
+        assert_eq!(animation.sample_with_parameter(0, 0.75, mode), Some(12));
+        assert_eq!(animation.sample_with_parameter(0, 1.2, mode), Some(13));
+    }
+
+    #[test]
+    fn test_existing_anim_format_loads() {
+        let animation: ParameterizedSpriteAnimation = ron::de::from_str(
+            "(base_frames:[1,0],parameters:1,parameter_offset:8,single_frame_duration:0.195)",
+        )
+        .unwrap();
+        assert!(animation.frame_durations.is_empty());
+        assert_eq!(animation.frame_duration(1), 0.195);
     }
 }
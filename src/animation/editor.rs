@@ -0,0 +1,504 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{prelude::*, sprite::Rect};
+use bevy_egui::EguiContext;
+
+use super::{
+    assets::SpriteAnimationAssetDef,
+    component_types::ParameterizedSpriteAnimationSet,
+    data_types::{ParameterizedSpriteAnimation, PlaybackMode},
+    hitboxes::{Hitbox, HitboxType, SpriteSheetHitboxes},
+};
+
+const ASSETS_DIR: &str = "assets";
+const ATLAS_TEXTURE_ID: u64 = 0x5e11;
+const HITBOX_TYPES: [HitboxType; 3] = [HitboxType::Hit, HitboxType::Block, HitboxType::Hurt];
+
+// Asset paths are relative to the assets folder.
+fn asset_file(path: &str) -> PathBuf {
+    PathBuf::from(ASSETS_DIR).join(path)
+}
+
+fn hitbox_color(hitbox_type: HitboxType) -> egui::Color32 {
+    match hitbox_type {
+        HitboxType::Hit => egui::Color32::RED,
+        HitboxType::Block => egui::Color32::LIGHT_BLUE,
+        HitboxType::Hurt => egui::Color32::GREEN,
+        HitboxType::User(_) => egui::Color32::YELLOW,
+    }
+}
+
+// Frame rects in atlas pixels, in the same order the loader adds them to the TextureAtlas.
+fn frame_rects(asset_def: &SpriteAnimationAssetDef) -> Vec<Rect> {
+    if !asset_def.packed_frames.is_empty() {
+        return asset_def
+            .packed_frames
+            .iter()
+            .map(|frame| frame.rect())
+            .collect();
+    }
+    let tile = asset_def.atlas_tile_size;
+    (0..asset_def.atlas_rows)
+        .flat_map(|row| (0..asset_def.atlas_columns).map(move |column| (row, column)))
+        .map(|(row, column)| {
+            let min = Vec2::new(column as f32, row as f32) * tile;
+            Rect {
+                min,
+                max: min + tile,
+            }
+        })
+        .collect()
+}
+
+// Hitboxes are relative to this point of the frame, in pixels from the frame's top-left.
+fn frame_origin(asset_def: &SpriteAnimationAssetDef, sprite_idx: usize) -> Vec2 {
+    asset_def
+        .packed_frames
+        .get(sprite_idx)
+        .map_or(asset_def.atlas_tile_size / 2.0, |frame| frame.pivot())
+}
+
+// Edits a .sprite file and the .anim file it points to. The files are read and written directly,
+// and saving reloads the game's copies through the asset watcher in debug builds.
+pub struct SpriteEditor {
+    pub open: bool,
+    pub sprite_path: String,
+    pub asset_def: Option<SpriteAnimationAssetDef>,
+    // Whether the .sprite file had hitboxes. Sheets without any are saved without them.
+    pub had_hitboxes: bool,
+    pub animation_set: ParameterizedSpriteAnimationSet,
+    pub atlas: Handle<Image>,
+    pub selected_animation: Option<String>,
+    pub selected_frame: usize,
+    pub new_animation_name: String,
+    pub hitbox_type: HitboxType,
+    pub drag_start: Option<egui::Pos2>,
+    pub zoom: f32,
+    pub playing: bool,
+    pub time: f32,
+    pub status: Option<String>,
+}
+
+impl Default for SpriteEditor {
+    fn default() -> Self {
+        SpriteEditor {
+            open: false,
+            sprite_path: "sprites/Player.sprite".to_string(),
+            asset_def: None,
+            had_hitboxes: false,
+            animation_set: ParameterizedSpriteAnimationSet::default(),
+            atlas: Handle::default(),
+            selected_animation: None,
+            selected_frame: 0,
+            new_animation_name: String::new(),
+            hitbox_type: HitboxType::Hit,
+            drag_start: None,
+            zoom: 4.0,
+            playing: false,
+            time: 0.0,
+            status: None,
+        }
+    }
+}
+
+impl SpriteEditor {
+    pub fn load(&mut self, asset_server: &AssetServer) -> anyhow::Result<()> {
+        let bytes = fs::read(asset_file(&self.sprite_path))?;
+        let mut asset_def = ron::de::from_bytes::<SpriteAnimationAssetDef>(&bytes)?;
+        let animation_bytes = fs::read(asset_file(&asset_def.animation_path))?;
+        self.animation_set =
+            ron::de::from_bytes::<ParameterizedSpriteAnimationSet>(&animation_bytes)?;
+        let frames = frame_rects(&asset_def).len();
+        self.had_hitboxes = asset_def.hitboxes.is_some();
+        asset_def
+            .hitboxes
+            .get_or_insert_with(|| SpriteSheetHitboxes::with_frames(frames));
+        self.atlas = asset_server.load(asset_def.atlas_path.as_str());
+        self.asset_def = Some(asset_def);
+        self.selected_animation = self.animation_set.animation_names().min().cloned();
+        self.selected_frame = 0;
+        self.time = 0.0;
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let asset_def = match &mut self.asset_def {
+            Some(asset_def) => asset_def,
+            None => anyhow::bail!("Nothing loaded"),
+        };
+        fs::write(
+            asset_file(&asset_def.animation_path),
+            ron::ser::to_string_pretty(&self.animation_set, ron::ser::PrettyConfig::new())?,
+        )?;
+        // Leave out the empty hitboxes load added, then put them back for further editing.
+        let hitboxes = asset_def.hitboxes.take();
+        asset_def.hitboxes = hitboxes
+            .clone()
+            .filter(|hitboxes| self.had_hitboxes || !hitboxes.is_empty());
+        let sprite = ron::ser::to_string_pretty(&*asset_def, ron::ser::PrettyConfig::new());
+        let saved_hitboxes = asset_def.hitboxes.is_some();
+        asset_def.hitboxes = hitboxes;
+        fs::write(asset_file(&self.sprite_path), sprite?)?;
+        self.had_hitboxes = saved_hitboxes;
+        Ok(())
+    }
+
+    fn selected_animation_mut(&mut self) -> Option<&mut ParameterizedSpriteAnimation> {
+        let name = self.selected_animation.clone()?;
+        self.animation_set.get_animation_mut(&name)
+    }
+
+    // The atlas frame under the preview: the playing frame, or the selected frame when paused.
+    fn preview_sprite(&self) -> Option<usize> {
+        let animation = self
+            .selected_animation
+            .as_ref()
+            .and_then(|name| self.animation_set.get_animation(name))?;
+        if self.playing {
+            animation.sample_with_parameter(0, self.time, PlaybackMode::Loop)
+        } else {
+            animation.get_frame_with_parameter(self.selected_frame, 0)
+        }
+    }
+}
+
+fn animation_list_ui(ui: &mut egui::Ui, editor: &mut SpriteEditor) {
+    let mut names: Vec<String> = editor.animation_set.animation_names().cloned().collect();
+    names.sort();
+    ui.horizontal_wrapped(|ui| {
+        for name in names {
+            let selected = editor.selected_animation.as_ref() == Some(&name);
+            if ui.selectable_label(selected, &name).clicked() {
+                editor.selected_animation = Some(name);
+                editor.selected_frame = 0;
+                editor.time = 0.0;
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut editor.new_animation_name);
+        if ui.button("New animation").clicked() && !editor.new_animation_name.is_empty() {
+            let name = std::mem::take(&mut editor.new_animation_name);
+            editor
+                .animation_set
+                .add_new_animation(name.clone(), ParameterizedSpriteAnimation::new(0, 1, 0.1));
+            editor.selected_animation = Some(name);
+            editor.selected_frame = 0;
+        }
+    });
+}
+
+fn animation_frames_ui(ui: &mut egui::Ui, editor: &mut SpriteEditor) {
+    let mut selected_frame = editor.selected_frame;
+    let animation = match editor.selected_animation_mut() {
+        Some(animation) => animation,
+        None => return,
+    };
+    ui.horizontal(|ui| {
+        ui.label("Frame duration");
+        ui.add(egui::DragValue::new(&mut animation.single_frame_duration).speed(0.005));
+        ui.label("Parameters");
+        ui.add(egui::DragValue::new(&mut animation.parameters).clamp_range(1..=64));
+        ui.label("Parameter offset");
+        ui.add(egui::DragValue::new(&mut animation.parameter_offset));
+    });
+    let mut remove = None;
+    for frame_idx in 0..animation.len() {
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(frame_idx == selected_frame, format!("Frame {}", frame_idx))
+                .clicked()
+            {
+                selected_frame = frame_idx;
+            }
+            let mut sprite_idx = animation
+                .get_frame_with_parameter(frame_idx, 0)
+                .unwrap_or(0);
+            if ui.add(egui::DragValue::new(&mut sprite_idx)).changed() {
+                animation.set_frame(frame_idx, sprite_idx);
+            }
+            let mut duration = animation.frame_duration(frame_idx);
+            if ui
+                .add(egui::DragValue::new(&mut duration).speed(0.005).suffix("s"))
+                .changed()
+            {
+                animation.set_frame_duration(frame_idx, duration.max(0.0));
+            }
+            if ui.button("Remove").clicked() {
+                remove = Some(frame_idx);
+            }
+        });
+    }
+    if let Some(frame_idx) = remove {
+        animation.remove_frame(frame_idx);
+        selected_frame = selected_frame.min(animation.len().saturating_sub(1));
+    }
+    if ui.button("Add frame").clicked() {
+        let last = animation.len().checked_sub(1);
+        let sprite_idx = last
+            .and_then(|last| animation.get_frame_with_parameter(last, 0))
+            .map_or(0, |sprite_idx| sprite_idx + 1);
+        animation.add_frame(sprite_idx);
+        selected_frame = animation.len() - 1;
+    }
+    editor.selected_frame = selected_frame;
+}
+
+// Shows the previewed frame zoomed in with its hitboxes. Dragging on it adds a hitbox of the
+// selected type.
+fn frame_preview_ui(ui: &mut egui::Ui, editor: &mut SpriteEditor, image_size: Vec2) {
+    let asset_def = match &editor.asset_def {
+        Some(asset_def) => asset_def,
+        None => return,
+    };
+    let rects = frame_rects(asset_def);
+    let sprite_idx = match editor.preview_sprite() {
+        Some(sprite_idx) if sprite_idx < rects.len() => sprite_idx,
+        _ => {
+            ui.label("No frame to preview");
+            return;
+        }
+    };
+    let rect = rects[sprite_idx];
+    let origin = frame_origin(asset_def, sprite_idx);
+    let uv = egui::Rect::from_min_max(
+        egui::pos2(rect.min.x / image_size.x, rect.min.y / image_size.y),
+        egui::pos2(rect.max.x / image_size.x, rect.max.y / image_size.y),
+    );
+    let size = (rect.max - rect.min) * editor.zoom;
+    let response = ui.add(
+        egui::Image::new(egui::TextureId::User(ATLAS_TEXTURE_ID), [size.x, size.y])
+            .uv(uv)
+            .sense(egui::Sense::drag()),
+    );
+    let zoom = editor.zoom;
+    let screen_origin = response.rect.min + egui::vec2(origin.x, origin.y) * zoom;
+    let to_screen = |point: Vec2| screen_origin + egui::vec2(point.x, point.y) * zoom;
+    let to_sprite = |pos: egui::Pos2| {
+        let offset = (pos - screen_origin) / zoom;
+        Vec2::new(offset.x.round(), offset.y.round())
+    };
+
+    let painter = ui.painter_at(response.rect);
+    painter.circle_filled(screen_origin, 2.0, egui::Color32::WHITE);
+    let hitboxes = asset_def
+        .hitboxes
+        .as_ref()
+        .and_then(|hitboxes| hitboxes.get_hitboxes(sprite_idx))
+        .cloned()
+        .unwrap_or_default();
+    for hitbox in hitboxes.iter() {
+        let (min, max) = hitbox.bounds();
+        painter.rect_stroke(
+            egui::Rect::from_two_pos(to_screen(min), to_screen(max)),
+            0.0,
+            (1.0, hitbox_color(hitbox.hitbox_type)),
+        );
+    }
+
+    if response.drag_started() {
+        editor.drag_start = response.interact_pointer_pos();
+    }
+    let pointer = ui.input().pointer.interact_pos();
+    if let (Some(start), Some(end)) = (editor.drag_start, pointer) {
+        painter.rect_stroke(
+            egui::Rect::from_two_pos(start, end),
+            0.0,
+            (1.0, hitbox_color(editor.hitbox_type)),
+        );
+        if response.drag_released() {
+            let (start, end) = (to_sprite(start), to_sprite(end));
+            if start.x != end.x && start.y != end.y {
+                let hitbox = Hitbox::rect(editor.hitbox_type, start.min(end), start.max(end));
+                if let Some(hitboxes) = editor
+                    .asset_def
+                    .as_mut()
+                    .and_then(|asset_def| asset_def.hitboxes.as_mut())
+                {
+                    hitboxes.add_hitbox(sprite_idx, hitbox);
+                }
+            }
+            editor.drag_start = None;
+        }
+    }
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Sprite {}", sprite_idx));
+        egui::ComboBox::from_label("New hitbox")
+            .selected_text(format!("{:?}", editor.hitbox_type))
+            .show_ui(ui, |ui| {
+                for hitbox_type in HITBOX_TYPES {
+                    ui.selectable_value(
+                        &mut editor.hitbox_type,
+                        hitbox_type,
+                        format!("{:?}", hitbox_type),
+                    );
+                }
+            });
+    });
+    let mut remove = None;
+    for (hitbox_idx, hitbox) in hitboxes.iter().enumerate() {
+        let (min, max) = hitbox.bounds();
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                hitbox_color(hitbox.hitbox_type),
+                format!(
+                    "{:?} ({}, {}) to ({}, {})",
+                    hitbox.hitbox_type, min.x, min.y, max.x, max.y
+                ),
+            );
+            if ui.button("Remove").clicked() {
+                remove = Some(hitbox_idx);
+            }
+        });
+    }
+    if let Some(hitbox_idx) = remove {
+        if let Some(hitboxes) = editor
+            .asset_def
+            .as_mut()
+            .and_then(|asset_def| asset_def.hitboxes.as_mut())
+        {
+            hitboxes.remove_hitbox(sprite_idx, hitbox_idx);
+        }
+    }
+}
+
+// The whole atlas with frame outlines. Clicking a frame appends it to the selected animation.
+fn atlas_preview_ui(ui: &mut egui::Ui, editor: &mut SpriteEditor, image_size: Vec2) {
+    let rects = match &editor.asset_def {
+        Some(asset_def) => frame_rects(asset_def),
+        None => return,
+    };
+    let mut clicked = None;
+    egui::ScrollArea::both().max_height(256.0).show(ui, |ui| {
+        let response = ui.add(
+            egui::Image::new(
+                egui::TextureId::User(ATLAS_TEXTURE_ID),
+                [image_size.x, image_size.y],
+            )
+            .sense(egui::Sense::click()),
+        );
+        let painter = ui.painter_at(response.rect);
+        let to_screen = |point: Vec2| response.rect.min + egui::vec2(point.x, point.y);
+        let preview = editor.preview_sprite();
+        for (sprite_idx, rect) in rects.iter().enumerate() {
+            let color = if preview == Some(sprite_idx) {
+                egui::Color32::WHITE
+            } else {
+                egui::Color32::DARK_GRAY
+            };
+            painter.rect_stroke(
+                egui::Rect::from_min_max(to_screen(rect.min), to_screen(rect.max)),
+                0.0,
+                (1.0, color),
+            );
+        }
+        if response.clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let pos = pos - response.rect.min;
+                clicked = rects.iter().position(|rect| {
+                    pos.x >= rect.min.x
+                        && pos.x < rect.max.x
+                        && pos.y >= rect.min.y
+                        && pos.y < rect.max.y
+                });
+            }
+        }
+    });
+    if let Some(sprite_idx) = clicked {
+        if let Some(animation) = editor.selected_animation_mut() {
+            animation.add_frame(sprite_idx);
+            editor.selected_frame = animation.len() - 1;
+        }
+    }
+}
+
+pub fn sprite_editor_system(
+    egui_context: Option<ResMut<EguiContext>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    time: Res<Time>,
+    mut editor: ResMut<SpriteEditor>,
+) {
+    let mut egui_context = match egui_context {
+        Some(egui_context) => egui_context,
+        None => return,
+    };
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        editor.open = !editor.open;
+    }
+    if !editor.open {
+        return;
+    }
+    egui_context.set_egui_texture(ATLAS_TEXTURE_ID, editor.atlas.clone());
+    if editor.playing {
+        editor.time += time.delta_seconds();
+    }
+    let image_size = images.get(&editor.atlas).map(|image| {
+        let size = image.texture_descriptor.size;
+        Vec2::new(size.width as f32, size.height as f32)
+    });
+
+    let mut open = editor.open;
+    egui::Window::new("Sprite editor")
+        .open(&mut open)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Sprite");
+                ui.text_edit_singleline(&mut editor.sprite_path);
+                if ui.button("Load").clicked() {
+                    editor.status = match editor.load(&asset_server) {
+                        Ok(()) => None,
+                        Err(err) => Some(format!("Could not load: {}", err)),
+                    };
+                }
+                if ui.button("Save").clicked() {
+                    editor.status = Some(match editor.save() {
+                        Ok(()) => format!("Saved {}", editor.sprite_path),
+                        Err(err) => format!("Could not save: {}", err),
+                    });
+                }
+            });
+            if let Some(status) = &editor.status {
+                ui.label(status);
+            }
+            if editor.asset_def.is_none() {
+                return;
+            }
+            ui.separator();
+            animation_list_ui(ui, &mut editor);
+            ui.separator();
+            animation_frames_ui(ui, &mut editor);
+            ui.separator();
+            let image_size = match image_size {
+                Some(image_size) => image_size,
+                None => {
+                    ui.label("Loading atlas...");
+                    return;
+                }
+            };
+            ui.horizontal(|ui| {
+                let mut playing = editor.playing;
+                if ui.checkbox(&mut playing, "Play").changed() {
+                    editor.playing = playing;
+                    editor.time = 0.0;
+                }
+                ui.label("Zoom");
+                ui.add(egui::DragValue::new(&mut editor.zoom).clamp_range(1.0..=16.0));
+            });
+            frame_preview_ui(ui, &mut editor, image_size);
+            ui.separator();
+            atlas_preview_ui(ui, &mut editor, image_size);
+        });
+    editor.open = open;
+}
+
+pub struct SpriteEditorPlugin;
+
+impl Plugin for SpriteEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpriteEditor>()
+            .add_system(sprite_editor_system);
+    }
+}
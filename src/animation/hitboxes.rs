@@ -1,21 +1,127 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum HitboxType {
     Hit,
     Block,
+    Hurt,
     User(u8),
 }
 
+// Shapes are in the same pixel space as a hitbox's min and max, with y pointing down. Rect is the
+// box between min and max, so sprite files without a shape keep loading as before.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub enum HitboxShape {
+    #[default]
+    Rect,
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Capsule {
+        a: Vec2,
+        b: Vec2,
+        radius: f32,
+    },
+    RotatedRect {
+        center: Vec2,
+        half_extents: Vec2,
+        angle: f32,
+    },
+    ConvexPolygon(Vec<Vec2>),
+}
+
+impl HitboxShape {
+    pub fn flip_x(&mut self) {
+        let flip = |point: &mut Vec2| point.x = -point.x;
+        match self {
+            HitboxShape::Rect => {}
+            HitboxShape::Circle { center, .. } => flip(center),
+            HitboxShape::Capsule { a, b, .. } => {
+                flip(a);
+                flip(b);
+            }
+            HitboxShape::RotatedRect { center, angle, .. } => {
+                flip(center);
+                *angle = -*angle;
+            }
+            HitboxShape::ConvexPolygon(points) => points.iter_mut().for_each(flip),
+        }
+    }
+
+    // None for Rect, whose bounds are the hitbox's min and max.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        match self {
+            HitboxShape::Rect => None,
+            HitboxShape::Circle { center, radius } => Some((
+                *center - Vec2::splat(*radius),
+                *center + Vec2::splat(*radius),
+            )),
+            HitboxShape::Capsule { a, b, radius } => Some((
+                a.min(*b) - Vec2::splat(*radius),
+                a.max(*b) + Vec2::splat(*radius),
+            )),
+            HitboxShape::RotatedRect {
+                center,
+                half_extents,
+                angle,
+            } => {
+                let (sin, cos) = angle.sin_cos();
+                let extents = Vec2::new(
+                    (half_extents.x * cos).abs() + (half_extents.y * sin).abs(),
+                    (half_extents.x * sin).abs() + (half_extents.y * cos).abs(),
+                );
+                Some((*center - extents, *center + extents))
+            }
+            HitboxShape::ConvexPolygon(points) => points.iter().fold(None, |bounds, point| {
+                Some(match bounds {
+                    Some((min, max)) => (point.min(min), point.max(max)),
+                    None => (*point, *point),
+                })
+            }),
+        }
+    }
+}
+
 #[derive(Component, Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Hitbox {
     pub hitbox_type: HitboxType,
+    // Only Rect needs min and max in the file; other shapes bring their own bounds.
+    #[serde(default)]
     pub min: Vec2,
+    #[serde(default)]
     pub max: Vec2,
+    #[serde(default)]
+    pub shape: HitboxShape,
 }
 
 impl Hitbox {
+    pub fn rect(hitbox_type: HitboxType, min: Vec2, max: Vec2) -> Self {
+        Hitbox {
+            hitbox_type,
+            min,
+            max,
+            shape: HitboxShape::Rect,
+        }
+    }
+
+    // min and max are kept as the shape's bounds, for anything that only cares about the box.
+    pub fn with_shape(hitbox_type: HitboxType, shape: HitboxShape) -> Self {
+        let (min, max) = shape.bounds().unwrap_or_default();
+        Hitbox {
+            hitbox_type,
+            min,
+            max,
+            shape,
+        }
+    }
+
+    // The shape's bounds, or min and max for Rect.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        self.shape.bounds().unwrap_or((self.min, self.max))
+    }
+
     pub fn get_center(&self) -> Vec2 {
         Vec2::new(self.min.x + self.max.x, -self.min.y - self.max.y) / 2.0
     }
@@ -34,6 +140,7 @@ impl Hitbox {
         println!("{:?} {:?} -> {:?} {:?}", self.min, self.max, min, max);
         self.min = min;
         self.max = max;
+        self.shape.flip_x();
     }
 }
 
@@ -44,12 +151,21 @@ pub struct SpriteSheetHitboxes {
 
 impl SpriteSheetHitboxes {
     pub fn from_texture_atlas(texture_atlas: &TextureAtlas) -> Self {
+        Self::with_frames(texture_atlas.textures.len())
+    }
+    pub fn with_frames(frames: usize) -> Self {
         let mut hitboxes = Vec::new();
-        hitboxes.resize_with(texture_atlas.textures.len(), || vec![]);
+        hitboxes.resize_with(frames, Vec::new);
         SpriteSheetHitboxes { hitboxes }
     }
     pub fn is_for(&self, texture_atlas: &TextureAtlas) -> bool {
-        texture_atlas.textures.len() == self.hitboxes.len()
+        texture_atlas.textures.len() == self.frames()
+    }
+    pub fn frames(&self) -> usize {
+        self.hitboxes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.hitboxes.iter().all(Vec::is_empty)
     }
     pub fn add_hitbox(&mut self, idx: usize, hitbox: Hitbox) {
         if let Some(hitboxes) = self.hitboxes.get_mut(idx) {
@@ -64,4 +180,61 @@ impl SpriteSheetHitboxes {
     pub fn get_hitboxes(&self, idx: usize) -> Option<&Vec<Hitbox>> {
         self.hitboxes.get(idx)
     }
+    pub fn has_hitbox_type(&self, hitbox_type: &HitboxType) -> bool {
+        self.hitboxes
+            .iter()
+            .flatten()
+            .any(|hitbox| hitbox.hitbox_type == *hitbox_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_hitbox_without_shape_loads() {
+        let hitbox: Hitbox =
+            ron::de::from_str("(hitbox_type:Hit,min:(-2,-46),max:(62,14))").unwrap();
+        assert_eq!(
+            hitbox,
+            Hitbox::rect(
+                HitboxType::Hit,
+                Vec2::new(-2.0, -46.0),
+                Vec2::new(62.0, 14.0)
+            )
+        );
+    }
+
+    #[test]
+    fn test_shaped_hitbox_flips() {
+        let mut hitbox = Hitbox::with_shape(
+            HitboxType::Hit,
+            HitboxShape::RotatedRect {
+                center: Vec2::new(10.0, 4.0),
+                half_extents: Vec2::new(8.0, 2.0),
+                angle: 0.5,
+            },
+        );
+        hitbox.flip_x();
+        assert_eq!(
+            hitbox.shape,
+            HitboxShape::RotatedRect {
+                center: Vec2::new(-10.0, 4.0),
+                half_extents: Vec2::new(8.0, 2.0),
+                angle: -0.5,
+            }
+        );
+        assert_eq!(Some((hitbox.min, hitbox.max)), hitbox.shape.bounds());
+    }
+
+    #[test]
+    fn test_shaped_hitbox_without_bounds_loads() {
+        let hitbox: Hitbox =
+            ron::de::from_str("(hitbox_type:Hit,shape:Circle(center:(8,-4),radius:6))").unwrap();
+        assert_eq!(
+            hitbox.bounds(),
+            (Vec2::new(2.0, -10.0), Vec2::new(14.0, 2.0))
+        );
+    }
 }
@@ -1,28 +1,91 @@
+pub mod aseprite;
 pub mod assets;
 pub mod bundles;
 pub mod component_types;
+pub mod controller;
 pub mod data_types;
+#[cfg(feature = "sprite_editor")]
+pub mod editor;
 pub mod hitboxes;
+pub mod parameters;
+pub mod pivots;
 pub mod systems;
+pub mod validation;
 // LD50 note: Pulled in from a personal project.
 
-use bevy::prelude::*;
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::timestep::{GameplayAppExt, GameplayLabel};
 
 use self::{
-    assets::{sprite_animation_unpack_system, SpriteAnimationAsset, SpriteAnimationAssetLoader},
-    component_types::AnimationState,
+    assets::{
+        sprite_animation_dependency_reload_system, sprite_animation_reload_system,
+        sprite_animation_unpack_system, AsepriteSheetLoader, ParameterizedSpriteAnimationSetLoader,
+        SpriteAnimationAsset, SpriteAnimationAssetLoader,
+    },
+    component_types::{AnimationEvent, AnimationState, ParameterizedSpriteAnimationSet},
+    controller::{animation_controller_system, AnimationController, AnimationControllerLoader},
+    parameters::{
+        animation_parameter_system, AnimationParameter, AnimationVariant, FacingDirections,
+    },
+    pivots::sprite_pivot_system,
     systems::{animation_timer_system, direction_parameter_animation_system},
 };
 
+// Order within GameplayLabel::Animate. The controller and the timer both write AnimationState, so
+// the controller picks the state, parameters pick the variant and the timer advances the result.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AnimationSystem {
+    Controller,
+    Parameter,
+    Timer,
+}
+
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animation_timer_system)
-            .add_system(direction_parameter_animation_system)
-            .register_type::<AnimationState>()
-            .add_asset::<SpriteAnimationAsset>()
-            .init_asset_loader::<SpriteAnimationAssetLoader>()
-            .add_system(sprite_animation_unpack_system);
+        app.add_gameplay_system(
+            animation_controller_system
+                .label(GameplayLabel::Animate)
+                .label(AnimationSystem::Controller)
+                .after(GameplayLabel::Damage),
+        )
+        .add_gameplay_system(
+            animation_parameter_system
+                .label(GameplayLabel::Animate)
+                .label(AnimationSystem::Parameter)
+                .after(AnimationSystem::Controller),
+        )
+        .add_gameplay_system(
+            animation_timer_system
+                .label(GameplayLabel::Animate)
+                .label(AnimationSystem::Timer)
+                .after(AnimationSystem::Parameter),
+        )
+        .add_gameplay_system(
+            direction_parameter_animation_system
+                .label(GameplayLabel::Sample)
+                .after(GameplayLabel::Animate),
+        )
+        .add_gameplay_event::<AnimationEvent>()
+        .register_type::<AnimationState>()
+        .register_type::<AnimationParameter>()
+        .register_type::<FacingDirections>()
+        .register_type::<AnimationVariant>()
+        .add_asset::<AnimationController>()
+        .init_asset_loader::<AnimationControllerLoader>()
+        .add_asset::<SpriteAnimationAsset>()
+        .init_asset_loader::<SpriteAnimationAssetLoader>()
+        .init_asset_loader::<AsepriteSheetLoader>()
+        .add_asset::<ParameterizedSpriteAnimationSet>()
+        .init_asset_loader::<ParameterizedSpriteAnimationSetLoader>()
+        .add_system(sprite_animation_unpack_system)
+        .add_system(sprite_animation_reload_system)
+        .add_system(sprite_animation_dependency_reload_system)
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            sprite_pivot_system.after(TransformSystem::TransformPropagate),
+        );
     }
 }
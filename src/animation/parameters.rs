@@ -0,0 +1,94 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::RigidBodyVelocityComponent;
+
+// Selects which parameter row of a ParameterizedSpriteAnimation plays. Rows past the end of an
+// animation fall back to its last row.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct AnimationParameter(pub usize);
+
+// Sheets laid out with one row per facing, starting facing right and going counter-clockwise.
+// Facing only changes while moving faster than min_speed, so entities keep looking the way they
+// last moved.
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct FacingDirections {
+    pub directions: usize,
+    pub min_speed: f32,
+    pub facing: usize,
+}
+
+impl FacingDirections {
+    pub fn new(directions: usize, min_speed: f32) -> Self {
+        FacingDirections {
+            directions,
+            min_speed,
+            facing: 0,
+        }
+    }
+}
+
+// An equipment or palette variant. Each variant is a block of facing rows, so the final row is
+// facing + directions * variant.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct AnimationVariant(pub usize);
+
+pub fn direction_index(direction: Vec2, directions: usize) -> usize {
+    if directions == 0 {
+        return 0;
+    }
+    let sector = TAU / directions as f32;
+    let angle = direction.y.atan2(direction.x).rem_euclid(TAU);
+    (angle / sector).round() as usize % directions
+}
+
+// Only drives entities with a facing or variant, so AnimationParameter can also be set by hand.
+#[allow(clippy::type_complexity)]
+pub fn animation_parameter_system(
+    mut query: Query<
+        (
+            &mut AnimationParameter,
+            Option<&mut FacingDirections>,
+            Option<&AnimationVariant>,
+            Option<&RigidBodyVelocityComponent>,
+        ),
+        Or<(With<FacingDirections>, With<AnimationVariant>)>,
+    >,
+) {
+    for (mut parameter, facing, variant, velocity) in query.iter_mut() {
+        let (facing, directions) = match (facing, velocity) {
+            (Some(mut facing), Some(velocity)) => {
+                let linvel = Vec2::new(velocity.linvel.x, velocity.linvel.y);
+                if linvel.length() > facing.min_speed {
+                    facing.facing = direction_index(linvel, facing.directions);
+                }
+                (facing.facing, facing.directions.max(1))
+            }
+            (Some(facing), None) => (facing.facing, facing.directions.max(1)),
+            _ => (0, 1),
+        };
+        let new_parameter = AnimationParameter(facing + directions * variant.map_or(0, |v| v.0));
+        if *parameter != new_parameter {
+            *parameter = new_parameter;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_index() {
+        assert_eq!(direction_index(Vec2::new(1.0, 0.0), 4), 0);
+        assert_eq!(direction_index(Vec2::new(0.0, 1.0), 4), 1);
+        assert_eq!(direction_index(Vec2::new(-1.0, 0.1), 4), 2);
+        assert_eq!(direction_index(Vec2::new(0.2, -1.0), 4), 3);
+        assert_eq!(direction_index(Vec2::new(1.0, -0.1), 4), 0);
+        assert_eq!(direction_index(Vec2::new(1.0, 1.0), 8), 1);
+        assert_eq!(direction_index(Vec2::new(-1.0, -1.0), 8), 5);
+    }
+}
@@ -0,0 +1,94 @@
+use bevy::{prelude::*, sprite::Rect};
+use serde::{Deserialize, Serialize};
+
+// One frame of a packed atlas, in pixels from the image's top-left. The pivot is measured from the
+// frame's top-left and defaults to the frame's center.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackedFrame {
+    pub min: Vec2,
+    pub max: Vec2,
+    #[serde(default)]
+    pub pivot: Option<Vec2>,
+}
+
+impl PackedFrame {
+    pub fn rect(&self) -> Rect {
+        Rect {
+            min: self.min,
+            max: self.max,
+        }
+    }
+
+    pub fn pivot(&self) -> Vec2 {
+        self.pivot.unwrap_or((self.max - self.min) / 2.0)
+    }
+
+    // From the pivot to the frame's center, in pixels with y pointing down.
+    pub fn center_offset(&self) -> Vec2 {
+        (self.max - self.min) / 2.0 - self.pivot()
+    }
+}
+
+// Per-frame pivots of a packed sheet. Entities are drawn with the current frame's pivot on their
+// origin, so hitboxes, which are relative to the origin, are relative to the pivot as well.
+#[derive(Component, Debug, Clone, Default)]
+pub struct SpritePivots {
+    offsets: Vec<Vec2>,
+    applied: Vec3,
+}
+
+impl SpritePivots {
+    pub fn from_frames(frames: &[PackedFrame]) -> Self {
+        SpritePivots {
+            offsets: frames.iter().map(PackedFrame::center_offset).collect(),
+            applied: Vec3::ZERO,
+        }
+    }
+
+    pub fn center_offset(&self, idx: usize) -> Vec2 {
+        self.offsets.get(idx).copied().unwrap_or(Vec2::ZERO)
+    }
+}
+
+// Sprites are always drawn centered, so the offset to the pivot goes on the GlobalTransform after
+// propagation. Gameplay and physics keep working with the untouched Transform. Propagation only
+// rewrites GlobalTransform when Transform changes, so the offset already applied is tracked.
+pub fn sprite_pivot_system(
+    mut query: Query<(
+        &mut SpritePivots,
+        &TextureAtlasSprite,
+        ChangeTrackers<Transform>,
+        &mut GlobalTransform,
+    )>,
+) {
+    for (mut pivots, sprite, transform_tracker, mut global_transform) in query.iter_mut() {
+        if transform_tracker.is_changed() {
+            pivots.applied = Vec3::ZERO;
+        }
+        let offset = pivots.center_offset(sprite.index);
+        let flip = if sprite.flip_x { -1.0 } else { 1.0 };
+        let offset = Vec3::new(offset.x * flip, -offset.y, 0.0) * global_transform.scale;
+        let offset = global_transform.rotation * offset;
+        if offset != pivots.applied {
+            global_transform.translation += offset - pivots.applied;
+            pivots.applied = offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_frame_offsets() {
+        let frames: Vec<PackedFrame> = ron::de::from_str(
+            "[(min:(0,0),max:(32,48),pivot:Some((16,44))),(min:(32,0),max:(96,32))]",
+        )
+        .unwrap();
+        let pivots = SpritePivots::from_frames(&frames);
+        assert_eq!(pivots.center_offset(0), Vec2::new(0.0, -20.0));
+        assert_eq!(pivots.center_offset(1), Vec2::ZERO);
+        assert_eq!(frames[1].rect().max, Vec2::new(96.0, 32.0));
+    }
+}
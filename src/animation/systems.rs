@@ -1,29 +1,62 @@
 use bevy::prelude::*;
 
-use super::component_types::{AnimationState, ParameterizedSpriteAnimationSet};
+use crate::timestep::{FixedTime, HitStop};
 
-pub fn animation_timer_system(time: Res<Time>, mut query: Query<&mut AnimationState>) {
-    query.for_each_mut(|mut animation_state| {
-        animation_state.timer.tick(time.delta());
+use super::{
+    component_types::{AnimationEvent, AnimationState, ParameterizedSpriteAnimationSet},
+    parameters::AnimationParameter,
+};
+
+pub fn animation_timer_system(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut AnimationState, Option<&HitStop>)>,
+) {
+    query.for_each_mut(|(mut animation_state, hit_stop)| {
+        let delta = fixed_time
+            .entity_delta(hit_stop)
+            .mul_f32(animation_state.speed);
+        animation_state.timer.tick(delta);
     });
 }
 
 pub fn direction_parameter_animation_system(
     mut query: Query<(
-        &AnimationState,
+        Entity,
+        &mut AnimationState,
         &mut TextureAtlasSprite,
         &ParameterizedSpriteAnimationSet,
+        Option<&AnimationParameter>,
     )>,
+    mut animation_events: EventWriter<AnimationEvent>,
 ) {
-    query.for_each_mut(|(animation_state, mut sprite, animation_set)| {
-        if let Some(animation) = animation_set.get_animation(animation_state.get_animation()) {
-            if let Some(new_index) = animation.sample_with_parameter(
-                0,
-                animation_state.timer.elapsed_secs(),
-                animation_state.looping,
-            ) {
-                sprite.index = new_index;
+    query.for_each_mut(
+        |(entity, mut animation_state, mut sprite, animation_set, parameter)| {
+            if let Some(animation) = animation_set.get_animation(animation_state.get_animation()) {
+                let elapsed = animation_state.timer.elapsed_secs();
+                let parameter =
+                    animation.clamp_parameter(parameter.map_or(0, |parameter| parameter.0));
+                if let Some(new_index) =
+                    animation.sample_with_parameter(parameter, elapsed, animation_state.mode)
+                {
+                    sprite.index = new_index;
+                }
+                if let Some(frames) = animation.frames_elapsed(elapsed, animation_state.mode) {
+                    if animation_state.sampled_frames != Some(frames) {
+                        for name in animation.events_crossed(
+                            animation_state.sampled_frames,
+                            frames,
+                            animation_state.mode,
+                        ) {
+                            animation_events.send(AnimationEvent {
+                                entity,
+                                animation: animation_state.get_animation().clone(),
+                                name: name.to_string(),
+                            });
+                        }
+                        animation_state.sampled_frames = Some(frames);
+                    }
+                }
             }
-        }
-    })
+        },
+    )
 }
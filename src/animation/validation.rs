@@ -0,0 +1,165 @@
+use super::{
+    assets::SpriteAnimationAssetDef, component_types::ParameterizedSpriteAnimationSet,
+    data_types::PlaybackMode, hitboxes::SpriteSheetHitboxes,
+};
+
+// Every check returns the problems it found instead of stopping at the first, so a sheet can be
+// fixed in one go.
+
+// Number of frames the loader will put in the sprite's TextureAtlas.
+pub fn atlas_frames(asset_def: &SpriteAnimationAssetDef) -> usize {
+    if asset_def.packed_frames.is_empty() {
+        asset_def.atlas_columns * asset_def.atlas_rows
+    } else {
+        asset_def.packed_frames.len()
+    }
+}
+
+pub fn validate_asset_path(path: &str) -> Vec<String> {
+    if path.contains('\\') {
+        vec![format!(
+            "\"{}\" uses backslashes, which only work on Windows. Use '/' instead",
+            path
+        )]
+    } else {
+        vec![]
+    }
+}
+
+pub fn validate_sprite_def(asset_def: &SpriteAnimationAssetDef) -> Vec<String> {
+    let mut problems = validate_asset_path(&asset_def.atlas_path);
+    problems.extend(validate_asset_path(&asset_def.animation_path));
+    if asset_def.packed_frames.is_empty() {
+        let tile = asset_def.atlas_tile_size;
+        if tile.x <= 0.0 || tile.y <= 0.0 || atlas_frames(asset_def) == 0 {
+            problems.push(
+                "needs either packed_frames or atlas_tile_size, atlas_columns and atlas_rows"
+                    .to_string(),
+            );
+        }
+    }
+    for (frame_idx, frame) in asset_def.packed_frames.iter().enumerate() {
+        if frame.max.x <= frame.min.x || frame.max.y <= frame.min.y {
+            problems.push(format!("packed frame {} has no area", frame_idx));
+        }
+    }
+    problems
+}
+
+// Every parameter row of every frame has to land inside the atlas, including the last row at
+// parameter_offset * (parameters - 1) past the base frame.
+pub fn validate_animation_set(
+    animation_set: &ParameterizedSpriteAnimationSet,
+    atlas_frames: usize,
+) -> Vec<String> {
+    let mut problems = vec![];
+    let mut names: Vec<&String> = animation_set.animation_names().collect();
+    names.sort();
+    for name in names {
+        let animation = match animation_set.get_animation(name) {
+            Some(animation) => animation,
+            None => continue,
+        };
+        if animation.len() == 0 {
+            problems.push(format!("animation \"{}\" has no frames", name));
+        }
+        if animation.parameters == 0 {
+            problems.push(format!(
+                "animation \"{}\" has no parameters, so it never shows a frame",
+                name
+            ));
+        }
+        for frame_idx in 0..animation.len() {
+            for parameter in 0..animation.parameters {
+                let sprite_idx = animation
+                    .get_frame_with_parameter(frame_idx, parameter)
+                    .unwrap_or_default();
+                if sprite_idx >= atlas_frames {
+                    problems.push(format!(
+                        "animation \"{}\" frame {} with parameter {} is sprite {}, but the atlas has {} frames",
+                        name, frame_idx, parameter, sprite_idx, atlas_frames
+                    ));
+                }
+            }
+        }
+        let negative_duration = animation.single_frame_duration < 0.0
+            || animation
+                .frame_durations
+                .iter()
+                .any(|duration| *duration < 0.0);
+        if negative_duration {
+            problems.push(format!(
+                "animation \"{}\" has a negative frame duration",
+                name
+            ));
+        } else if animation.len() > 0 && animation.pass_duration(PlaybackMode::Once) <= 0.0 {
+            problems.push(format!("animation \"{}\" takes no time to play", name));
+        }
+        for event in animation.events.iter() {
+            if event.frame >= animation.len() {
+                problems.push(format!(
+                    "animation \"{}\" fires \"{}\" on frame {}, but it has {} frames",
+                    name,
+                    event.name,
+                    event.frame,
+                    animation.len()
+                ));
+            }
+        }
+    }
+    problems
+}
+
+pub fn validate_hitboxes(hitboxes: &SpriteSheetHitboxes, atlas_frames: usize) -> Vec<String> {
+    if hitboxes.frames() == atlas_frames {
+        vec![]
+    } else {
+        vec![format!(
+            "hitboxes are listed for {} frames, but the atlas has {} frames",
+            hitboxes.frames(),
+            atlas_frames
+        )]
+    }
+}
+
+pub fn ensure_valid(problems: Vec<String>) -> anyhow::Result<()> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!("{}", problems.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_finds_problems() {
+        let asset_def: SpriteAnimationAssetDef = ron::de::from_str(
+            r#"(atlas_tile_size:(16,16),atlas_columns:2,atlas_rows:2,atlas_path:"sprites\\Bolt.png",
+                animation_path:"sprites/Bolt.anim",hitboxes:Some((hitboxes:[[],[],[]])))"#,
+        )
+        .unwrap();
+        assert_eq!(atlas_frames(&asset_def), 4);
+        assert_eq!(validate_sprite_def(&asset_def).len(), 1);
+        assert_eq!(
+            validate_hitboxes(asset_def.hitboxes.as_ref().unwrap(), 4).len(),
+            1
+        );
+
+        let animation_set: ParameterizedSpriteAnimationSet = ron::de::from_str(
+            r#"(animations:{
+                "Idle":(base_frames:[0,1],parameters:2,parameter_offset:2,single_frame_duration:0.1),
+                "Spin":(base_frames:[1],parameters:2,parameter_offset:3,single_frame_duration:0.1,
+                    events:[(frame:1,name:"hit")]),
+            })"#,
+        )
+        .unwrap();
+        let problems = validate_animation_set(&animation_set, atlas_frames(&asset_def));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("sprite 4"));
+        assert!(problems[1].contains("\"hit\""));
+        assert!(ensure_valid(problems).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{layers::CollisionLayer, prelude::*};
 
 pub struct WorldEntityBuilder {
     pub radius: f32,
@@ -39,6 +39,16 @@ impl WorldEntityBuilder {
         }
     }
 
+    pub fn in_layer(self, layer: CollisionLayer) -> Self {
+        Self {
+            collision_group: layer.bit(),
+            collision_filter: CollisionLayer::mask(layer.touches()),
+            solver_group: layer.bit(),
+            solver_filter: CollisionLayer::mask(layer.solid_against()),
+            ..self
+        }
+    }
+
     pub fn with_collision_group(self, collision_group: u32) -> Self {
         Self {
             collision_group,
@@ -0,0 +1,121 @@
+// Lints every .sprite and .anim file under assets/ with the same checks the sprite loader runs,
+// and exits with an error code if any of them has problems.
+//
+//     cargo run --bin validate_sprites [assets dir]
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ludum_dare_50::animation::{
+    assets::SpriteAnimationAssetDef,
+    component_types::ParameterizedSpriteAnimationSet,
+    validation::{atlas_frames, validate_animation_set, validate_hitboxes, validate_sprite_def},
+};
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext == extension)
+}
+
+fn read_animation_set(path: &Path) -> anyhow::Result<ParameterizedSpriteAnimationSet> {
+    let bytes = fs::read(path)?;
+    Ok(ron::de::from_bytes::<ParameterizedSpriteAnimationSet>(
+        &bytes,
+    )?)
+}
+
+// Checks a .sprite file along with the .anim file it points to, returning that .anim file so it
+// isn't checked again on its own.
+fn check_sprite(
+    assets_dir: &Path,
+    path: &Path,
+    problems: &mut Vec<String>,
+) -> anyhow::Result<PathBuf> {
+    let bytes = fs::read(path)?;
+    let asset_def = ron::de::from_bytes::<SpriteAnimationAssetDef>(&bytes)?;
+    problems.extend(validate_sprite_def(&asset_def));
+    let frames = atlas_frames(&asset_def);
+    if let Some(hitboxes) = &asset_def.hitboxes {
+        problems.extend(validate_hitboxes(hitboxes, frames));
+    }
+    if !assets_dir.join(&asset_def.atlas_path).is_file() {
+        problems.push(format!("atlas \"{}\" doesn't exist", asset_def.atlas_path));
+    }
+    let animation_path = assets_dir.join(&asset_def.animation_path);
+    match read_animation_set(&animation_path) {
+        Ok(animation_set) => problems.extend(validate_animation_set(&animation_set, frames)),
+        Err(err) => problems.push(format!(
+            "could not read \"{}\": {}",
+            asset_def.animation_path, err
+        )),
+    }
+    Ok(animation_path)
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let assets_dir = PathBuf::from(args.get(1).map_or("assets", String::as_str));
+    let mut files = vec![];
+    if let Err(err) = collect_files(&assets_dir, &mut files) {
+        println!("Could not read {}: {}", assets_dir.display(), err);
+        std::process::exit(2);
+    }
+    files.sort();
+
+    let mut failed = 0;
+    let mut report = |path: &Path, problems: Vec<String>| {
+        if !problems.is_empty() {
+            failed += 1;
+            println!("{}:", path.display());
+            for problem in problems {
+                println!("    {}", problem);
+            }
+        }
+    };
+
+    let mut checked_animations = HashSet::new();
+    let sprites = files.iter().filter(|path| has_extension(path, "sprite"));
+    for path in sprites.clone() {
+        let mut problems = vec![];
+        match check_sprite(&assets_dir, path, &mut problems) {
+            Ok(animation_path) => {
+                checked_animations.insert(animation_path);
+            }
+            Err(err) => problems.push(err.to_string()),
+        }
+        report(path, problems);
+    }
+    // .anim files no sprite uses can't be checked against an atlas, only for what's inside them.
+    let animations = files.iter().filter(|path| has_extension(path, "anim"));
+    for path in animations.clone() {
+        if checked_animations.contains(path) {
+            continue;
+        }
+        let problems = match read_animation_set(path) {
+            Ok(animation_set) => validate_animation_set(&animation_set, usize::MAX),
+            Err(err) => vec![err.to_string()],
+        };
+        report(path, problems);
+    }
+
+    let checked = sprites.count() + animations.count();
+    if failed > 0 {
+        println!("{} of {} files have problems", failed, checked);
+        std::process::exit(1);
+    }
+    println!("Checked {} files", checked);
+}
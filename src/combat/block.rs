@@ -0,0 +1,342 @@
+use crate::{
+    animation::hitboxes::{Hitbox, HitboxType},
+    prelude::*,
+    projectiles::Projectile,
+    timestep::FixedTime,
+};
+
+// Entities with a Guard block with any Block hitboxes on their current frame. Blocking in the
+// first parry_window seconds after the guard goes up parries, staggering the attacker. Only the
+// hitboxes and projectiles that touched a Block hitbox are blocked, so a second hitbox of the same
+// attack can still land elsewhere.
+#[derive(Component, Debug, Reflect, Clone, Default)]
+pub struct Guard {
+    pub parry_window: f32,
+    pub stagger: f32,
+    pub raised_for: f32,
+    pub raised: bool,
+    pub blocking: Vec<Entity>,
+}
+
+impl Guard {
+    pub fn new(parry_window: f32, stagger: f32) -> Self {
+        Guard {
+            parry_window,
+            stagger,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_parrying(&self) -> bool {
+        self.raised && self.raised_for <= self.parry_window
+    }
+
+    pub fn is_blocking(&self, collider: Entity) -> bool {
+        self.blocking.contains(&collider)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Blocked {
+    pub attacker: Entity,
+    pub defender: Entity,
+    pub parried: bool,
+}
+
+pub const GUARD: CollisionHandler = "guard";
+
+pub fn hitbox_owner(
+    collider_entity: Entity,
+    hitbox_type: HitboxType,
+    hitbox_query: &Query<(&Hitbox, &Parent)>,
+) -> Option<Entity> {
+    hitbox_query
+        .get(collider_entity)
+        .ok()
+        .filter(|(hitbox, _parent)| hitbox.hitbox_type == hitbox_type)
+        .map(|(_hitbox, parent)| parent.0)
+}
+
+pub fn guard_system(
+    fixed_time: Res<FixedTime>,
+    mut collisions: EventReader<RoutedCollision>,
+    mut guard_query: Query<(&mut Guard, Option<&Children>)>,
+    hitbox_query: Query<(&Hitbox, &Parent)>,
+    projectile_query: Query<&Projectile>,
+    mut blocked_events: EventWriter<Blocked>,
+) {
+    for (mut guard, children) in guard_query.iter_mut() {
+        guard.raised = has_child(&children, |child| {
+            hitbox_owner(**child, HitboxType::Block, &hitbox_query).is_some()
+        });
+        if guard.raised {
+            guard.raised_for += fixed_time.delta_seconds();
+            // Parked hitboxes and spent projectiles don't report that they stopped touching.
+            guard.blocking.retain(|collider| {
+                hitbox_query.get(*collider).is_ok() || projectile_query.get(*collider).is_ok()
+            });
+        } else {
+            guard.raised_for = 0.0;
+            guard.blocking.clear();
+        }
+    }
+    // The first collider is a Block hitbox, the second a Hit hitbox or a projectile, which is
+    // blocked on behalf of whoever fired it.
+    for collision in collisions
+        .iter()
+        .filter(|collision| collision.is_for(GUARD))
+    {
+        let defender = collision.first_owner;
+        let attacker = projectile_query
+            .get(collision.second_owner)
+            .map_or(collision.second_owner, |projectile| projectile.source);
+        let collider = collision.second;
+        if let Ok((mut guard, _children)) = guard_query.get_mut(defender) {
+            if !collision.started {
+                guard.blocking.retain(|blocked| *blocked != collider);
+            } else if guard.raised && !guard.is_blocking(collider) {
+                guard.blocking.push(collider);
+                blocked_events.send(Blocked {
+                    attacker,
+                    defender,
+                    parried: guard.is_parrying(),
+                });
+            }
+        }
+    }
+}
+
+pub fn parry_stagger_system(
+    mut blocked_events: EventReader<Blocked>,
+    guard_query: Query<&Guard>,
+    mut health_query: Query<&mut Health>,
+) {
+    for event in blocked_events.iter().filter(|event| event.parried) {
+        if let (Ok(guard), Ok(mut health)) = (
+            guard_query.get(event.defender),
+            health_query.get_mut(event.attacker),
+        ) {
+            health.stagger(guard.stagger);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+
+    fn spawn_with_hitbox(world: &mut World, hitbox_type: HitboxType) -> (Entity, Entity) {
+        let owner = world
+            .spawn()
+            .insert(Health::new(10))
+            .insert(Faction::Minions)
+            .id();
+        let hitbox = world
+            .spawn()
+            .insert(Hitbox::rect(hitbox_type, Vec2::ZERO, Vec2::ONE))
+            .insert(Parent(owner))
+            .id();
+        world.entity_mut(owner).insert(Children::with(&[hitbox]));
+        (owner, hitbox)
+    }
+
+    fn spawn_bolt(world: &mut World, archer: Entity) -> Entity {
+        world
+            .spawn()
+            .insert(Projectile {
+                source: archer,
+                faction: Some(Faction::Minions),
+                damage: 3,
+                piercing: false,
+                gravity_scale: 0.0,
+                lifetime: 1.0,
+                hit: vec![],
+                spent: false,
+            })
+            .id()
+    }
+
+    // A guarding player and the systems that decide whether its guard holds.
+    fn guarding_world() -> (World, SystemStage, (Entity, Entity)) {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::default());
+        world.insert_resource(Events::<RoutedCollision>::default());
+        world.insert_resource(Events::<Blocked>::default());
+        world.insert_resource(Events::<DamageEvent>::default());
+        world.insert_resource(Events::<DamageApplied>::default());
+        world.insert_resource(Events::<EntityKilled>::default());
+        let stage = SystemStage::single_threaded()
+            .with_system(guard_system.label("guard"))
+            .with_system(parry_stagger_system.after("guard"))
+            .with_system(resolve_damage_system.after("guard"));
+        let (defender, block) = spawn_with_hitbox(&mut world, HitboxType::Block);
+        world
+            .entity_mut(defender)
+            .insert(Faction::Player)
+            .insert(Guard::new(0.15, 0.6));
+        (world, stage, (defender, block))
+    }
+
+    // Runs one tick in which the attacker deals damage of the given kind through the collider.
+    fn hit(
+        world: &mut World,
+        stage: &mut SystemStage,
+        defender: Entity,
+        (attacker, collider): (Entity, Entity),
+        kind: DamageKind,
+    ) {
+        world
+            .get_resource_mut::<Events<DamageEvent>>()
+            .unwrap()
+            .send(DamageEvent {
+                source: attacker,
+                target: defender,
+                amount: 3,
+                knockback: Vec2::ZERO,
+                kind,
+                attack_id: None,
+                effect: None,
+                collider: Some(collider),
+            });
+        stage.run(world);
+    }
+
+    // Runs one tick in which the attacker's collider touches the defender's Block hitbox and the
+    // attacker deals damage of the given kind.
+    fn attack(
+        world: &mut World,
+        stage: &mut SystemStage,
+        (defender, block): (Entity, Entity),
+        (attacker, collider, category): (Entity, Entity, CollisionCategory),
+        kind: DamageKind,
+    ) {
+        let collider_owner = match category {
+            CollisionCategory::Hitbox(_) => attacker,
+            _ => collider,
+        };
+        world
+            .get_resource_mut::<Events<RoutedCollision>>()
+            .unwrap()
+            .send(RoutedCollision {
+                handler: GUARD,
+                kind: ContactKind::Intersection,
+                started: true,
+                first: block,
+                second: collider,
+                first_category: CollisionCategory::Hitbox(HitboxType::Block),
+                second_category: category,
+                first_owner: defender,
+                second_owner: collider_owner,
+            });
+        hit(world, stage, defender, (attacker, collider), kind);
+    }
+
+    #[test]
+    fn test_block_and_parry_stagger() {
+        let (mut world, mut stage, (defender, block)) = guarding_world();
+
+        // Right after the guard goes up, a sword is parried and its wielder staggers.
+        let (swordsman, sword) = spawn_with_hitbox(&mut world, HitboxType::Hit);
+        let sword_hit = (swordsman, sword, CollisionCategory::Hitbox(HitboxType::Hit));
+        attack(
+            &mut world,
+            &mut stage,
+            (defender, block),
+            sword_hit,
+            DamageKind::Melee,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 10);
+        assert_eq!(world.get::<Health>(swordsman).unwrap().hit_stun, 0.6);
+
+        // Once the parry window has passed, a bolt is blocked without staggering the archer.
+        world.get_mut::<Guard>(defender).unwrap().raised_for = 1.0;
+        let archer = world
+            .spawn()
+            .insert(Health::new(10))
+            .insert(Faction::Minions)
+            .id();
+        let bolt = spawn_bolt(&mut world, archer);
+        let bolt_hit = (archer, bolt, CollisionCategory::MinionProjectile);
+        attack(
+            &mut world,
+            &mut stage,
+            (defender, block),
+            bolt_hit,
+            DamageKind::Projectile,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 10);
+        assert!(!world.get::<Health>(archer).unwrap().in_hit_stun());
+
+        // With the guard down, hits land.
+        world.entity_mut(block).remove::<Hitbox>();
+        let (brawler, fist) = spawn_with_hitbox(&mut world, HitboxType::Hit);
+        let fist_hit = (brawler, fist, CollisionCategory::Hitbox(HitboxType::Hit));
+        attack(
+            &mut world,
+            &mut stage,
+            (defender, block),
+            fist_hit,
+            DamageKind::Melee,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 7);
+    }
+
+    // The guard only stops what touched it: the same attacker's other hitbox and other bolts land.
+    #[test]
+    fn test_hit_on_the_unguarded_side_lands() {
+        let (mut world, mut stage, (defender, block)) = guarding_world();
+        world.get_mut::<Guard>(defender).unwrap().raised_for = 1.0;
+
+        let (swordsman, sword) = spawn_with_hitbox(&mut world, HitboxType::Hit);
+        let tail = world
+            .spawn()
+            .insert(Hitbox::rect(HitboxType::Hit, Vec2::ZERO, Vec2::ONE))
+            .insert(Parent(swordsman))
+            .id();
+        world.entity_mut(swordsman).push_children(&[tail]);
+        let sword_hit = (swordsman, sword, CollisionCategory::Hitbox(HitboxType::Hit));
+        attack(
+            &mut world,
+            &mut stage,
+            (defender, block),
+            sword_hit,
+            DamageKind::Melee,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 10);
+        hit(
+            &mut world,
+            &mut stage,
+            defender,
+            (swordsman, tail),
+            DamageKind::Melee,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 7);
+
+        let archer = world
+            .spawn()
+            .insert(Health::new(10))
+            .insert(Faction::Minions)
+            .id();
+        let first_bolt = spawn_bolt(&mut world, archer);
+        let second_bolt = spawn_bolt(&mut world, archer);
+        let bolt_hit = (archer, first_bolt, CollisionCategory::MinionProjectile);
+        attack(
+            &mut world,
+            &mut stage,
+            (defender, block),
+            bolt_hit,
+            DamageKind::Projectile,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 7);
+        hit(
+            &mut world,
+            &mut stage,
+            defender,
+            (archer, second_bolt),
+            DamageKind::Projectile,
+        );
+        assert_eq!(world.get::<Health>(defender).unwrap().current_health, 4);
+    }
+}
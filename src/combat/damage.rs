@@ -0,0 +1,192 @@
+use crate::{
+    player::{AttackImpulses, PlayerState},
+    prelude::*,
+    terrain::GroundedState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageKind {
+    Contact,
+    Melee,
+    Projectile,
+    Status,
+}
+
+// Sent by collision systems and damaging status effects. Nothing touches Health until
+// resolve_damage_system has seen it.
+#[derive(Debug, Clone)]
+pub struct DamageEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    pub knockback: Vec2,
+    pub kind: DamageKind,
+    pub attack_id: Option<u32>,
+    pub effect: Option<StatusEffect>,
+    // The hitbox sensor, projectile or body that touched the target, if a collision dealt this.
+    pub collider: Option<Entity>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DamageApplied {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    pub kind: DamageKind,
+    pub attack_id: Option<u32>,
+    // The velocity the target was launched with, zero when it wasn't knocked back.
+    pub knockback: Vec2,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntityKilled {
+    pub entity: Entity,
+    pub killer: Entity,
+}
+
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct HitReaction {
+    pub hit_stun: f32,
+    pub invulnerability: f32,
+}
+
+impl HitReaction {
+    pub fn new(hit_stun: f32, invulnerability: f32) -> Self {
+        HitReaction {
+            hit_stun,
+            invulnerability,
+        }
+    }
+}
+
+#[derive(Component, Debug, Reflect, Inspectable, Clone)]
+pub struct DamageModifiers {
+    pub damage_taken: f32,
+    pub flat_reduction: i32,
+    pub knockback_taken: f32,
+}
+
+impl Default for DamageModifiers {
+    fn default() -> Self {
+        DamageModifiers {
+            damage_taken: 1.0,
+            flat_reduction: 0,
+            knockback_taken: 1.0,
+        }
+    }
+}
+
+impl DamageModifiers {
+    pub fn modify_damage(&self, amount: i32, vulnerability: f32) -> i32 {
+        ((amount as f32 * self.damage_taken * vulnerability).round() as i32 - self.flat_reduction)
+            .max(0)
+    }
+
+    pub fn modify_knockback(&self, knockback: Vec2) -> Vec2 {
+        knockback * self.knockback_taken
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn resolve_damage_system(
+    mut damage_events: EventReader<DamageEvent>,
+    mut damage_applied: EventWriter<DamageApplied>,
+    mut entity_killed: EventWriter<EntityKilled>,
+    mut target_query: Query<(
+        &mut Health,
+        Option<&HitReaction>,
+        Option<&DamageModifiers>,
+        Option<&mut RigidBodyVelocityComponent>,
+        Option<&mut GroundedState>,
+        Option<&mut PlayerState>,
+        Option<&mut StatusEffects>,
+    )>,
+    mut attacker_query: Query<&mut AttackImpulses>,
+    guard_query: Query<&Guard>,
+    faction_query: Query<&Faction>,
+) {
+    for event in damage_events.iter() {
+        if event.kind != DamageKind::Status
+            && (event.source == event.target
+                || !are_hostile(
+                    faction_query.get(event.source).ok(),
+                    faction_query.get(event.target).ok(),
+                ))
+        {
+            continue;
+        }
+        if let Ok(guard) = guard_query.get(event.target) {
+            if event
+                .collider
+                .is_some_and(|collider| guard.is_blocking(collider))
+            {
+                continue;
+            }
+        }
+        if let Ok((
+            mut health,
+            reaction,
+            modifiers,
+            velocity,
+            grounded,
+            player_state,
+            status_effects,
+        )) = target_query.get_mut(event.target)
+        {
+            let default_modifiers = DamageModifiers::default();
+            let modifiers = modifiers.unwrap_or(&default_modifiers);
+            let reaction = reaction.cloned().unwrap_or_default();
+            let vulnerability = status_effects.as_ref().map_or(1.0, |status_effects| {
+                status_effects.damage_taken_multiplier()
+            });
+            let amount = modifiers.modify_damage(event.amount, vulnerability);
+            let knockback = modifiers.modify_knockback(event.knockback);
+            let was_alive = health.current_health > 0;
+            // Status damage ticks through i-frames and doesn't stagger.
+            if event.kind == DamageKind::Status {
+                health.current_health -= amount;
+            } else if !health.take_hit(amount, reaction.hit_stun, reaction.invulnerability) {
+                continue;
+            }
+            if let (Some(effect), Some(mut status_effects)) = (&event.effect, status_effects) {
+                status_effects.apply(effect.clone());
+            }
+            let mut applied_knockback = Vec2::ZERO;
+            if knockback != Vec2::ZERO {
+                if let Some(mut velocity) = velocity {
+                    velocity.linvel = knockback.into();
+                    applied_knockback = knockback;
+                }
+                if let Some(mut grounded) = grounded {
+                    grounded.lift_off();
+                }
+            }
+            if let (Some(mut player_state), false) =
+                (player_state, event.kind == DamageKind::Status)
+            {
+                *player_state = PlayerState::HitStun;
+            }
+            if let Some(attack_id) = event.attack_id {
+                if let Ok(mut impulses) = attacker_query.get_mut(event.source) {
+                    if impulses.attack_id == attack_id {
+                        impulses.hit_minions.push(event.target);
+                    }
+                }
+            }
+            damage_applied.send(DamageApplied {
+                source: event.source,
+                target: event.target,
+                amount,
+                kind: event.kind,
+                attack_id: event.attack_id,
+                knockback: applied_knockback,
+            });
+            if was_alive && health.current_health <= 0 {
+                entity_killed.send(EntityKilled {
+                    entity: event.target,
+                    killer: event.source,
+                });
+            }
+        }
+    }
+}
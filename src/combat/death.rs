@@ -0,0 +1,109 @@
+use crate::{
+    animation::{
+        component_types::{AnimationState, ParameterizedSpriteAnimationSet},
+        controller::Animator,
+        hitboxes::SpriteSheetHitboxes,
+    },
+    layers::CollisionLayer,
+    prelude::*,
+    sensors::{HitboxSensor, HitboxSensorPool},
+};
+
+pub const DEATH_ANIMATION: &str = "Death";
+
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct Dying {
+    pub animated: bool,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn death_system(
+    mut commands: Commands,
+    mut dying_query: Query<
+        (
+            Entity,
+            &Health,
+            Option<&Children>,
+            Option<&ParameterizedSpriteAnimationSet>,
+            Option<&mut AnimationState>,
+            Option<&mut Animator>,
+            Option<&mut ColliderFlagsComponent>,
+            Option<&mut ContactType>,
+        ),
+        Without<Dying>,
+    >,
+    hitbox_query: Query<(), With<HitboxSensor>>,
+) {
+    for (
+        entity,
+        health,
+        children,
+        animation_set,
+        animation_state,
+        animator,
+        collider_flags,
+        contact_type,
+    ) in dying_query.iter_mut()
+    {
+        if health.current_health > 0 {
+            continue;
+        }
+        let animated = animation_set
+            .and_then(|animation_set| animation_set.get_animation(&DEATH_ANIMATION.to_string()))
+            .is_some();
+        if let (true, Some(mut animation_state)) = (animated, animation_state) {
+            *animation_state = AnimationState::once(DEATH_ANIMATION.to_string());
+        }
+        if let Some(mut animator) = animator {
+            animator.paused = true;
+        }
+        if let Some(mut collider_flags) = collider_flags {
+            collider_flags.collision_groups = CollisionLayer::Corpse.collision_groups();
+            collider_flags.solver_groups = CollisionLayer::Corpse.solver_groups();
+            collider_flags.active_events = ActiveEvents::empty();
+        }
+        if let Some(mut contact_type) = contact_type {
+            *contact_type = ContactType::Inactive;
+        }
+        if let Some(children) = children {
+            let sensors: Vec<Entity> = children
+                .iter()
+                .copied()
+                .filter(|child| hitbox_query.get(*child).is_ok())
+                .collect();
+            // Detach first so the parent's `Children` doesn't keep stale ids.
+            commands.entity(entity).remove_children(&sensors);
+            for sensor in sensors {
+                commands.entity(sensor).despawn_recursive();
+            }
+        }
+        commands
+            .entity(entity)
+            .remove::<SpriteSheetHitboxes>()
+            .remove::<HitboxSensorPool>()
+            .insert(Dying { animated });
+    }
+}
+
+pub fn despawn_dead_system(
+    mut commands: Commands,
+    dead_query: Query<(
+        Entity,
+        &Dying,
+        Option<&ParameterizedSpriteAnimationSet>,
+        Option<&AnimationState>,
+    )>,
+) {
+    for (entity, dying, animation_set, animation_state) in dead_query.iter() {
+        let finished = match (dying.animated, animation_set, animation_state) {
+            (true, Some(animation_set), Some(animation_state)) => {
+                animation_state.get_animation() != DEATH_ANIMATION
+                    || animation_set.animation_complete(animation_state)
+            }
+            _ => true,
+        };
+        if finished {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -17,6 +17,35 @@ impl Health {
             invulnerability: 0.0,
         }
     }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerability > 0.0
+    }
+
+    pub fn in_hit_stun(&self) -> bool {
+        self.hit_stun > 0.0
+    }
+
+    // Returns false, leaving health untouched, if the hit landed during i-frames.
+    pub fn take_hit(&mut self, damage: i32, hit_stun: f32, invulnerability: f32) -> bool {
+        if self.is_invulnerable() {
+            return false;
+        }
+        self.current_health -= damage;
+        self.hit_stun = hit_stun;
+        self.invulnerability = invulnerability;
+        true
+    }
+
+    // Stun without damage, which ignores i-frames so a parry always lands.
+    pub fn stagger(&mut self, hit_stun: f32) {
+        self.hit_stun = self.hit_stun.max(hit_stun);
+    }
+
+    pub fn tick(&mut self, delta_seconds: f32) {
+        self.hit_stun = (self.hit_stun - delta_seconds).max(0.0);
+        self.invulnerability = (self.invulnerability - delta_seconds).max(0.0);
+    }
 }
 
 #[derive(Component, Debug, Reflect, Inspectable, Clone)]
@@ -27,6 +56,7 @@ pub enum ContactType {
     Inactive,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for ContactType {
     fn default() -> Self {
         ContactType::Inactive
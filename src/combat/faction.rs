@@ -0,0 +1,31 @@
+use crate::prelude::*;
+
+// Who fights whom. Damage only lands when the source is hostile to the target, so a minion moved to
+// the Player faction is charmed, Neutral creatures never start a fight but can be hurt, and Feral
+// creatures fight everything, each other included.
+#[derive(Component, Debug, Reflect, Inspectable, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Faction {
+    Player,
+    Minions,
+    #[default]
+    Neutral,
+    Feral,
+}
+
+impl Faction {
+    pub fn is_hostile_to(&self, other: Faction) -> bool {
+        match (*self, other) {
+            (Faction::Neutral, _) => false,
+            (_, Faction::Neutral) | (Faction::Feral, _) | (_, Faction::Feral) => true,
+            (faction, other) => faction != other,
+        }
+    }
+}
+
+// Entities without a Faction are hostile to everyone, like before factions existed.
+pub fn are_hostile(source: Option<&Faction>, target: Option<&Faction>) -> bool {
+    match (source, target) {
+        (Some(source), Some(target)) => source.is_hostile_to(*target),
+        _ => true,
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    prelude::*,
+    timestep::{FixedTime, HitStop, FIXED_TIMESTEP},
+};
+
+pub const HIT_STOP_DURATION: f32 = 4.0 * FIXED_TIMESTEP;
+
+// Freezes both sides of every landed melee or contact hit, and only the victim of a projectile,
+// whose shooter is somewhere else. Bodies keep the velocity they had, knockback included, and get
+// it back once the stop runs out. A hit landing during a stop restarts it, but only a new knockback
+// replaces the held velocity, since the body's current velocity is the zero the stop holds it at.
+pub fn hit_stop_system(
+    mut commands: Commands,
+    mut damage_applied: EventReader<DamageApplied>,
+    mut stop_query: Query<(
+        Option<&mut HitStop>,
+        &mut RigidBodyVelocityComponent,
+        &mut RigidBodyForcesComponent,
+    )>,
+    mut stopped: Local<Vec<Entity>>,
+) {
+    stopped.clear();
+    for event in damage_applied
+        .iter()
+        .filter(|event| event.kind != DamageKind::Status)
+    {
+        let source = (event.kind != DamageKind::Projectile).then_some(event.source);
+        for entity in source.into_iter().chain([event.target]) {
+            if stopped.contains(&entity) {
+                continue;
+            }
+            if let Ok((hit_stop, mut velocity, mut forces)) = stop_query.get_mut(entity) {
+                stopped.push(entity);
+                if let Some(mut hit_stop) = hit_stop {
+                    hit_stop.remaining = HIT_STOP_DURATION;
+                    if entity == event.target && event.knockback != Vec2::ZERO {
+                        hit_stop.held_velocity = event.knockback;
+                    }
+                } else {
+                    commands.entity(entity).insert(HitStop {
+                        remaining: HIT_STOP_DURATION,
+                        held_velocity: velocity.linvel.into(),
+                        held_gravity_scale: forces.gravity_scale,
+                    });
+                    forces.gravity_scale = 0.0;
+                }
+                velocity.linvel = Vec2::ZERO.into();
+            }
+        }
+    }
+}
+
+// Runs after everything that steers bodies, so a stopped body stays put through the physics step.
+pub fn hit_stop_hold_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut stop_query: Query<(
+        Entity,
+        &mut HitStop,
+        &mut RigidBodyVelocityComponent,
+        &mut RigidBodyForcesComponent,
+    )>,
+) {
+    for (entity, mut hit_stop, mut velocity, mut forces) in stop_query.iter_mut() {
+        hit_stop.remaining -= fixed_time.delta_seconds();
+        if hit_stop.remaining > 0.0 {
+            velocity.linvel = Vec2::ZERO.into();
+        } else {
+            velocity.linvel = hit_stop.held_velocity.into();
+            forces.gravity_scale = hit_stop.held_gravity_scale;
+            commands.entity(entity).remove::<HitStop>();
+        }
+    }
+}
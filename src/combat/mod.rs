@@ -1,197 +1,264 @@
 use crate::{
-    minions::Minion,
-    player::{AttackImpulses, PlayerState},
+    animation::hitboxes::{Hitbox, HitboxType, SpriteSheetHitboxes},
+    player::AttackImpulses,
     prelude::*,
-    terrain::GroundedState,
+    timestep::{FixedTime, GameplayAppExt, GameplayLabel},
 };
+mod block;
+mod damage;
+mod death;
 mod entity;
+mod faction;
+mod hit_stop;
+mod routing;
+mod status;
+pub use block::*;
+pub use damage::*;
+pub use death::*;
 pub use entity::*;
+pub use faction::*;
+pub use hit_stop::*;
+pub use routing::*;
+pub use status::*;
 
-const HIT_LAUNCH_SPEED: f32 = 20.0;
+pub const HIT_LAUNCH_SPEED: f32 = 20.0;
+const BLINK_RATE: f32 = 12.0;
 
-fn player_hit_stun(
-    entity: Entity,
-    launch_dir: Vec2,
-    damage: i32,
-    health_query: &mut Query<&mut Health>,
-    velocity_query: &mut Query<&mut RigidBodyVelocityComponent>,
-    player_state_query: &mut Query<(&mut GroundedState, &mut PlayerState)>,
-) {
-    if let Ok(mut health) = health_query.get_mut(entity) {
-        health.current_health -= damage;
-    }
-    if let Ok(mut velocity) = velocity_query.get_mut(entity) {
-        if launch_dir.x > 0.0 {
-            velocity.linvel = Vec2::new(HIT_LAUNCH_SPEED, HIT_LAUNCH_SPEED).into();
-        } else {
-            velocity.linvel = Vec2::new(-HIT_LAUNCH_SPEED, HIT_LAUNCH_SPEED).into();
-        }
-    }
-    if let Ok((mut grounded_state, mut player_state)) = player_state_query.get_mut(entity) {
-        grounded_state.lift_off();
-        *player_state = PlayerState::HitStun;
-    }
-}
+pub const CONTACT_DAMAGE: CollisionHandler = "contact_damage";
+pub const MELEE_HIT: CollisionHandler = "melee_hit";
 
-fn get_position_hittables<'a>(
-    entity_a: Entity,
-    entity_b: Entity,
-    hittable_query: &'a Query<&ContactType>,
-    position_query: &'a Query<&RigidBodyPositionComponent>,
-) -> Option<(
-    &'a RigidBodyPositionComponent,
-    &'a RigidBodyPositionComponent,
-    &'a ContactType,
-    &'a ContactType,
-)> {
-    if let (Ok(position_a), Ok(position_b)) =
-        (position_query.get(entity_a), position_query.get(entity_b))
-    {
-        if let (Ok(hittable_a), Ok(hittable_b)) =
-            (hittable_query.get(entity_a), hittable_query.get(entity_b))
-        {
-            return Some((position_a, position_b, hittable_a, hittable_b));
-        }
+fn launch_away(launch_dir: Vec2) -> Vec2 {
+    if launch_dir.x > 0.0 {
+        Vec2::new(HIT_LAUNCH_SPEED, HIT_LAUNCH_SPEED)
+    } else {
+        Vec2::new(-HIT_LAUNCH_SPEED, HIT_LAUNCH_SPEED)
     }
-    None
 }
 
-fn player_hit_stun_system(
-    mut contact_events: EventReader<ContactEvent>,
+// The first collider of the pair takes damage from touching the second.
+fn contact_damage_system(
+    mut collisions: EventReader<RoutedCollision>,
     position_query: Query<&RigidBodyPositionComponent>,
-    hittable_query: Query<&ContactType>,
-    mut health_query: Query<&mut Health>,
-    mut player_state_query: Query<(&mut GroundedState, &mut PlayerState)>,
-    mut velocity_query: Query<&mut RigidBodyVelocityComponent>,
+    contact_query: Query<&ContactType>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
-    for event in contact_events.iter() {
-        match event {
-            ContactEvent::Started(a, b) => {
-                if let Some((position_a, position_b, hittable_a, hittable_b)) =
-                    get_position_hittables(a.entity(), b.entity(), &hittable_query, &position_query)
-                {
-                    let hit_def = match (hittable_a, hittable_b) {
-                        (ContactType::Player, ContactType::Minion(damage))
-                        | (ContactType::Player, ContactType::MinionProjectile(damage)) => Some((
-                            a.entity(),
-                            get_vec_to_target(position_b, position_a),
-                            damage,
-                        )),
-                        (ContactType::Minion(damage), ContactType::Player)
-                        | (ContactType::MinionProjectile(damage), ContactType::Player) => Some((
-                            b.entity(),
-                            get_vec_to_target(position_a, position_b),
-                            damage,
-                        )),
-                        _ => None,
-                    };
-                    if let Some((entity, launch_dir, damage)) = hit_def {
-                        player_hit_stun(
-                            entity,
-                            launch_dir,
-                            *damage,
-                            &mut health_query,
-                            &mut velocity_query,
-                            &mut player_state_query,
-                        );
-                    }
-                }
-            }
-            _ => {}
+    for collision in collisions
+        .iter()
+        .filter(|collision| collision.is_for(CONTACT_DAMAGE) && collision.started)
+    {
+        let damage = match contact_query.get(collision.second) {
+            Ok(ContactType::Minion(damage)) => *damage,
+            _ => continue,
+        };
+        if let (Ok(target_pos), Ok(source_pos)) = (
+            position_query.get(collision.first),
+            position_query.get(collision.second),
+        ) {
+            damage_events.send(DamageEvent {
+                source: collision.second,
+                target: collision.first,
+                amount: damage,
+                knockback: launch_away(get_vec_to_target(source_pos, target_pos)),
+                kind: DamageKind::Contact,
+                attack_id: None,
+                effect: None,
+                collider: Some(collision.second),
+            });
         }
     }
 }
 
-fn get_attacker(
+// Entities whose sprite sheet defines Hurt hitboxes can only be hit there. Everything else is hit
+// anywhere on its body.
+pub fn get_hurt_entity(
     collider_entity: Entity,
-    attacker_query: &Query<(Option<&Parent>, Option<&AttackImpulses>)>,
+    hitbox_query: &Query<(&Hitbox, &Parent)>,
+    sheet_query: &Query<Option<&SpriteSheetHitboxes>>,
 ) -> Option<Entity> {
-    if let Some((parent, m_attack)) = attacker_query.get(collider_entity).ok() {
-        if m_attack.is_some() {
-            Some(collider_entity)
-        } else if let Some(parent) = parent {
-            get_attacker(parent.0, attacker_query)
-        } else {
-            None
-        }
-    } else {
-        None
+    if let Some(owner) = hitbox_owner(collider_entity, HitboxType::Hurt, hitbox_query) {
+        return Some(owner);
     }
-}
-
-fn get_attack_minion(
-    entity_a: Entity,
-    entity_b: Entity,
-    attacker_query: &Query<(Option<&Parent>, Option<&AttackImpulses>)>,
-    minion_query: &Query<(
-        Entity,
-        &mut Minion,
-        &mut Health,
-        &mut GroundedState,
-        &mut RigidBodyVelocityComponent,
-    )>,
-) -> Option<(Entity, Entity)> {
-    if let Some(attacker) = get_attacker(entity_a, attacker_query) {
-        if let Ok(minion) = minion_query.get(entity_b) {
-            return Some((attacker, minion.0));
-        }
-    } else if let Some(attacker) = get_attacker(entity_b, attacker_query) {
-        if let Ok(minion) = minion_query.get(entity_a) {
-            return Some((attacker, minion.0));
-        }
+    match sheet_query.get(collider_entity) {
+        Ok(Some(hitboxes)) if hitboxes.has_hitbox_type(&HitboxType::Hurt) => None,
+        Ok(_) if hitbox_query.get(collider_entity).is_err() => Some(collider_entity),
+        _ => None,
     }
-    None
 }
 
-fn attack_hit_stun_system(
-    mut intersection_events: EventReader<IntersectionEvent>,
-    mut attacker_query: QuerySet<(
-        QueryState<(Option<&Parent>, Option<&AttackImpulses>)>,
-        QueryState<&mut AttackImpulses>,
-    )>,
-    mut minion_query: Query<(
-        Entity,
-        &mut Minion,
-        &mut Health,
-        &mut GroundedState,
-        &mut RigidBodyVelocityComponent,
-    )>,
+// The first collider is a Hit hitbox, the second a body or one of its hurtboxes. Whether the hit
+// lands on a friend is left to resolve_damage_system.
+fn attack_damage_system(
+    mut collisions: EventReader<RoutedCollision>,
+    attacker_query: Query<&AttackImpulses>,
+    hitbox_query: Query<(&Hitbox, &Parent)>,
+    sheet_query: Query<Option<&SpriteSheetHitboxes>>,
+    target_query: Query<(), With<Health>>,
+    position_query: Query<&RigidBodyPositionComponent>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
-    for event in intersection_events.iter() {
-        if !event.intersecting {
-            continue;
-        }
-        let entity_a = event.collider1.entity();
-        let entity_b = event.collider2.entity();
-        if let Some((attacker, minion)) =
-            get_attack_minion(entity_a, entity_b, &attacker_query.q0(), &mut minion_query)
-        {
-            if let (
-                Ok(mut impulses),
-                Ok((entity, mut minion, mut health, mut grounded, mut velocity)),
-            ) = (
-                attacker_query.q1().get_mut(attacker),
-                minion_query.get_mut(minion),
-            ) {
-                if minion.hit(impulses.attack_id) {
-                    impulses.hit_minions.push(entity);
-                    health.current_health -= impulses.attack_damage;
-                    velocity.linvel = Vec2::new(0.0, HIT_LAUNCH_SPEED).into();
-                    grounded.lift_off();
+    for collision in collisions
+        .iter()
+        .filter(|collision| collision.is_for(MELEE_HIT) && collision.started)
+    {
+        let attacker = collision.first_owner;
+        let target = match get_hurt_entity(collision.second, &hitbox_query, &sheet_query) {
+            Some(target) if target != attacker && target_query.get(target).is_ok() => target,
+            _ => continue,
+        };
+        if let Ok(impulses) = attacker_query.get(attacker) {
+            if impulses.hit_minions.contains(&target) {
+                continue;
+            }
+            let mut knockback = impulses.knockback;
+            if let (Ok(attacker_pos), Ok(target_pos)) =
+                (position_query.get(attacker), position_query.get(target))
+            {
+                if get_vec_to_target(attacker_pos, target_pos).x < 0.0 {
+                    knockback.x = -knockback.x;
                 }
             }
+            damage_events.send(DamageEvent {
+                source: attacker,
+                target,
+                amount: impulses.attack_damage,
+                knockback,
+                kind: DamageKind::Melee,
+                attack_id: Some(impulses.attack_id),
+                effect: impulses.on_hit_effect.clone(),
+                collider: Some(collision.first),
+            });
         }
     }
 }
 
+fn health_timer_system(fixed_time: Res<FixedTime>, mut health_query: Query<&mut Health>) {
+    for mut health in health_query.iter_mut() {
+        health.tick(fixed_time.delta_seconds());
+    }
+}
+
+fn invulnerability_blink_system(mut query: Query<(&Health, &mut TextureAtlasSprite)>) {
+    for (health, mut sprite) in query.iter_mut() {
+        let visible =
+            !health.is_invulnerable() || (health.invulnerability * BLINK_RATE) as i32 % 2 == 0;
+        sprite.color.set_a(if visible { 1.0 } else { 0.25 });
+    }
+}
+
 pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(player_hit_stun_system)
-            .add_system(attack_hit_stun_system)
+        app.init_resource::<CollisionRoutes>()
+            .add_gameplay_event::<RoutedCollision>()
+            .add_collision_route(
+                CollisionCategory::Player,
+                CollisionCategory::Minion,
+                ContactKind::Contact,
+                CONTACT_DAMAGE,
+            )
+            .add_collision_route(
+                CollisionCategory::Minion,
+                CollisionCategory::Minion,
+                ContactKind::Contact,
+                CONTACT_DAMAGE,
+            )
+            .add_collision_route(
+                CollisionCategory::Hitbox(HitboxType::Hit),
+                CollisionCategory::Player,
+                ContactKind::Intersection,
+                MELEE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::Hitbox(HitboxType::Hit),
+                CollisionCategory::Minion,
+                ContactKind::Intersection,
+                MELEE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::Hitbox(HitboxType::Hit),
+                CollisionCategory::Hitbox(HitboxType::Hurt),
+                ContactKind::Intersection,
+                MELEE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::Hitbox(HitboxType::Block),
+                CollisionCategory::Hitbox(HitboxType::Hit),
+                ContactKind::Intersection,
+                GUARD,
+            )
+            .add_collision_route(
+                CollisionCategory::Hitbox(HitboxType::Block),
+                CollisionCategory::MinionProjectile,
+                ContactKind::Intersection,
+                GUARD,
+            )
+            .add_gameplay_system(
+                collision_dispatch_system
+                    .after(GameplayLabel::Act)
+                    .before(GameplayLabel::Resolve),
+            )
+            .add_gameplay_system(
+                health_timer_system
+                    .label(GameplayLabel::Environment)
+                    .after(GameplayLabel::Input),
+            )
+            .add_gameplay_system(
+                status_effect_system
+                    .label(GameplayLabel::Environment)
+                    .after(GameplayLabel::Input),
+            )
+            .add_gameplay_system(invulnerability_blink_system.after(GameplayLabel::Sample))
+            .add_gameplay_system(
+                contact_damage_system
+                    .label(GameplayLabel::Resolve)
+                    .after(GameplayLabel::Act),
+            )
+            .add_gameplay_system(
+                attack_damage_system
+                    .label(GameplayLabel::Resolve)
+                    .after(GameplayLabel::Act),
+            )
+            .add_gameplay_system(
+                guard_system
+                    .label(GameplayLabel::Resolve)
+                    .after(GameplayLabel::Act),
+            )
+            .add_gameplay_system(
+                resolve_damage_system
+                    .label(GameplayLabel::Damage)
+                    .after(GameplayLabel::Resolve),
+            )
+            .add_gameplay_system(
+                death_system
+                    .after(GameplayLabel::Damage)
+                    .before(GameplayLabel::Animate),
+            )
+            .add_gameplay_system(hit_stop_system.after(GameplayLabel::Damage))
+            .add_gameplay_system(
+                hit_stop_hold_system
+                    .after(GameplayLabel::Resolve)
+                    .before(GameplayLabel::Damage),
+            )
+            .add_gameplay_system(parry_stagger_system.after(GameplayLabel::Resolve))
+            .add_gameplay_system(despawn_dead_system.after(GameplayLabel::Damage))
+            .add_gameplay_event::<DamageEvent>()
+            .add_gameplay_event::<Blocked>()
+            .add_gameplay_event::<DamageApplied>()
+            .add_gameplay_event::<EntityKilled>()
             .register_type::<Health>()
             .register_inspectable::<Health>()
+            .register_type::<Faction>()
+            .register_inspectable::<Faction>()
+            .register_type::<Guard>()
+            .register_type::<StatusEffects>()
+            .register_inspectable::<StatusEffects>()
+            .register_type::<Dying>()
+            .register_inspectable::<Dying>()
+            .register_type::<HitReaction>()
+            .register_inspectable::<HitReaction>()
+            .register_type::<DamageModifiers>()
+            .register_inspectable::<DamageModifiers>()
             .register_type::<ContactType>()
             .register_inspectable::<ContactType>();
     }
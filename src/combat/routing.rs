@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+
+use crate::{
+    animation::hitboxes::{Hitbox, HitboxType},
+    prelude::*,
+    terrain::TerrainBlock,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CollisionCategory {
+    Player,
+    Minion,
+    MinionProjectile,
+    Terrain,
+    Hitbox(HitboxType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContactKind {
+    // Solid bodies touching, from rapier's ContactEvent.
+    Contact,
+    // A sensor overlapping something, from rapier's IntersectionEvent.
+    Intersection,
+}
+
+pub type CollisionHandler = &'static str;
+
+// Which handlers hear about which pairs. A route for (A, B) is also used for (B, A), with the
+// collision turned around so the handler always sees A first. Pairs within one category are sent
+// both ways round.
+#[derive(Debug, Default)]
+pub struct CollisionRoutes {
+    routes: HashMap<(CollisionCategory, CollisionCategory, ContactKind), Vec<CollisionHandler>>,
+}
+
+impl CollisionRoutes {
+    pub fn register(
+        &mut self,
+        first: CollisionCategory,
+        second: CollisionCategory,
+        kind: ContactKind,
+        handler: CollisionHandler,
+    ) {
+        let handlers = self.routes.entry((first, second, kind)).or_default();
+        if !handlers.contains(&handler) {
+            handlers.push(handler);
+        }
+    }
+
+    pub fn handlers(
+        &self,
+        first: CollisionCategory,
+        second: CollisionCategory,
+        kind: ContactKind,
+    ) -> &[CollisionHandler] {
+        self.routes
+            .get(&(first, second, kind))
+            .map(|handlers| handlers.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+pub trait CollisionRoutingAppExt {
+    fn add_collision_route(
+        &mut self,
+        first: CollisionCategory,
+        second: CollisionCategory,
+        kind: ContactKind,
+        handler: CollisionHandler,
+    ) -> &mut Self;
+}
+
+impl CollisionRoutingAppExt for App {
+    fn add_collision_route(
+        &mut self,
+        first: CollisionCategory,
+        second: CollisionCategory,
+        kind: ContactKind,
+        handler: CollisionHandler,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(CollisionRoutes::default)
+            .register(first, second, kind, handler);
+        self
+    }
+}
+
+// One collision, sent to a single handler. first and second are the colliders, and the owners are
+// the bodies they belong to, which differ for hitbox sensors.
+#[derive(Debug, Clone)]
+pub struct RoutedCollision {
+    pub handler: CollisionHandler,
+    pub kind: ContactKind,
+    pub started: bool,
+    pub first: Entity,
+    pub second: Entity,
+    pub first_category: CollisionCategory,
+    pub second_category: CollisionCategory,
+    pub first_owner: Entity,
+    pub second_owner: Entity,
+}
+
+impl RoutedCollision {
+    pub fn is_for(&self, handler: CollisionHandler) -> bool {
+        self.handler == handler
+    }
+}
+
+#[derive(SystemParam)]
+pub struct CollisionCategories<'w, 's> {
+    contact_query: Query<'w, 's, &'static ContactType>,
+    hitbox_query: Query<'w, 's, (&'static Hitbox, Option<&'static Parent>)>,
+    terrain_query: Query<'w, 's, (), With<TerrainBlock>>,
+}
+
+impl<'w, 's> CollisionCategories<'w, 's> {
+    pub fn category(&self, collider_entity: Entity) -> Option<CollisionCategory> {
+        if let Ok((hitbox, _parent)) = self.hitbox_query.get(collider_entity) {
+            return Some(CollisionCategory::Hitbox(hitbox.hitbox_type));
+        }
+        if self.terrain_query.get(collider_entity).is_ok() {
+            return Some(CollisionCategory::Terrain);
+        }
+        match self.contact_query.get(collider_entity) {
+            Ok(ContactType::Player) => Some(CollisionCategory::Player),
+            Ok(ContactType::Minion(_)) => Some(CollisionCategory::Minion),
+            Ok(ContactType::MinionProjectile(_)) => Some(CollisionCategory::MinionProjectile),
+            _ => None,
+        }
+    }
+
+    pub fn owner(&self, collider_entity: Entity) -> Entity {
+        match self.hitbox_query.get(collider_entity) {
+            Ok((_hitbox, Some(parent))) => parent.0,
+            _ => collider_entity,
+        }
+    }
+}
+
+pub fn collision_dispatch_system(
+    routes: Res<CollisionRoutes>,
+    categories: CollisionCategories,
+    mut contact_events: EventReader<ContactEvent>,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    mut routed: EventWriter<RoutedCollision>,
+) {
+    let contacts = contact_events.iter().map(|event| match event {
+        ContactEvent::Started(a, b) => (ContactKind::Contact, a.entity(), b.entity(), true),
+        ContactEvent::Stopped(a, b) => (ContactKind::Contact, a.entity(), b.entity(), false),
+    });
+    let intersections = intersection_events.iter().map(|event| {
+        (
+            ContactKind::Intersection,
+            event.collider1.entity(),
+            event.collider2.entity(),
+            event.intersecting,
+        )
+    });
+    for (kind, entity_a, entity_b, started) in contacts.chain(intersections) {
+        let (category_a, category_b) =
+            match (categories.category(entity_a), categories.category(entity_b)) {
+                (Some(category_a), Some(category_b)) => (category_a, category_b),
+                _ => continue,
+            };
+        for (first, first_category, second, second_category) in [
+            (entity_a, category_a, entity_b, category_b),
+            (entity_b, category_b, entity_a, category_a),
+        ] {
+            for handler in routes.handlers(first_category, second_category, kind) {
+                routed.send(RoutedCollision {
+                    handler,
+                    kind,
+                    started,
+                    first,
+                    second,
+                    first_category,
+                    second_category,
+                    first_owner: categories.owner(first),
+                    second_owner: categories.owner(second),
+                });
+            }
+        }
+    }
+}
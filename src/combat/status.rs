@@ -0,0 +1,231 @@
+use crate::{prelude::*, timestep::FixedTime};
+
+#[derive(Debug, Reflect, Inspectable, Copy, Clone, PartialEq, Eq, Default)]
+pub enum StatusEffectKind {
+    #[default]
+    Poison,
+    Burn,
+    Slow,
+    Stun,
+    Vulnerable,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StackRule {
+    // Reapplying restarts the timer and keeps the stronger magnitude.
+    Refresh,
+    // Reapplying adds its duration to what is left.
+    Extend,
+    // Reapplying restarts the timer and adds a stack, up to the limit.
+    Intensify(u32),
+}
+
+impl StatusEffectKind {
+    pub fn stack_rule(&self) -> StackRule {
+        match self {
+            StatusEffectKind::Poison => StackRule::Intensify(5),
+            StatusEffectKind::Burn => StackRule::Refresh,
+            StatusEffectKind::Slow => StackRule::Refresh,
+            StatusEffectKind::Stun => StackRule::Extend,
+            StatusEffectKind::Vulnerable => StackRule::Refresh,
+        }
+    }
+
+    // Seconds between damage ticks, for the effects that deal damage.
+    pub fn tick_interval(&self) -> Option<f32> {
+        match self {
+            StatusEffectKind::Poison => Some(1.0),
+            StatusEffectKind::Burn => Some(0.5),
+            _ => None,
+        }
+    }
+}
+
+// magnitude is damage per tick for Poison and Burn, the fraction of speed lost for Slow and the
+// fraction of extra damage taken for Vulnerable.
+#[derive(Debug, Reflect, Inspectable, Default, Clone, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    pub remaining: f32,
+    pub magnitude: f32,
+    pub stacks: u32,
+    pub tick_timer: f32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusEffectKind, duration: f32, magnitude: f32) -> Self {
+        StatusEffect {
+            kind,
+            remaining: duration,
+            magnitude,
+            stacks: 1,
+            tick_timer: 0.0,
+        }
+    }
+
+    // Counts the effect down and returns how many damage ticks came due.
+    pub fn advance(&mut self, delta_seconds: f32) -> u32 {
+        self.remaining -= delta_seconds;
+        let interval = match self.kind.tick_interval() {
+            Some(interval) => interval,
+            None => return 0,
+        };
+        self.tick_timer += delta_seconds;
+        let mut ticks = 0;
+        while self.tick_timer >= interval {
+            self.tick_timer -= interval;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    pub fn tick_damage(&self) -> i32 {
+        (self.magnitude * self.stacks as f32).round() as i32
+    }
+}
+
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct StatusEffects {
+    // Shown through Inspectable; Vec reflection would need FromReflect on StatusEffect.
+    #[reflect(ignore)]
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let existing = self
+            .effects
+            .iter_mut()
+            .find(|existing| existing.kind == effect.kind);
+        match (existing, effect.kind.stack_rule()) {
+            (None, _) => self.effects.push(effect),
+            (Some(existing), StackRule::Refresh) => {
+                existing.remaining = existing.remaining.max(effect.remaining);
+                existing.magnitude = existing.magnitude.max(effect.magnitude);
+            }
+            (Some(existing), StackRule::Extend) => {
+                existing.remaining += effect.remaining;
+            }
+            (Some(existing), StackRule::Intensify(max_stacks)) => {
+                existing.remaining = existing.remaining.max(effect.remaining);
+                existing.stacks = (existing.stacks + 1).min(max_stacks);
+            }
+        }
+    }
+
+    pub fn get(&self, kind: StatusEffectKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.get(StatusEffectKind::Stun).is_some()
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        self.get(StatusEffectKind::Slow)
+            .map_or(1.0, |slow| (1.0 - slow.magnitude).max(0.0))
+    }
+
+    pub fn damage_taken_multiplier(&self) -> f32 {
+        self.get(StatusEffectKind::Vulnerable)
+            .map_or(1.0, |vulnerable| 1.0 + vulnerable.magnitude)
+    }
+}
+
+pub fn stunned(status_effects: Option<&StatusEffects>) -> bool {
+    status_effects.is_some_and(|status_effects| status_effects.is_stunned())
+}
+
+pub fn speed_multiplier(status_effects: Option<&StatusEffects>) -> f32 {
+    status_effects.map_or(1.0, |status_effects| status_effects.speed_multiplier())
+}
+
+// Counts down every effect and sends the damage ticks of the ones that hurt.
+pub fn status_effect_system(
+    fixed_time: Res<FixedTime>,
+    mut status_query: Query<(Entity, &mut StatusEffects)>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let delta_seconds = fixed_time.delta_seconds();
+    for (entity, mut status_effects) in status_query.iter_mut() {
+        for effect in status_effects.effects.iter_mut() {
+            for _ in 0..effect.advance(delta_seconds) {
+                damage_events.send(DamageEvent {
+                    source: entity,
+                    target: entity,
+                    amount: effect.tick_damage(),
+                    knockback: Vec2::ZERO,
+                    kind: DamageKind::Status,
+                    attack_id: None,
+                    effect: None,
+                    collider: None,
+                });
+            }
+        }
+        if status_effects
+            .effects
+            .iter()
+            .any(|effect| effect.remaining <= 0.0)
+        {
+            status_effects
+                .effects
+                .retain(|effect| effect.remaining > 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(effects: &[StatusEffect]) -> StatusEffect {
+        let mut status_effects = StatusEffects::default();
+        for effect in effects {
+            status_effects.apply(effect.clone());
+        }
+        assert_eq!(status_effects.effects.len(), 1);
+        status_effects.effects[0].clone()
+    }
+
+    #[test]
+    fn test_refresh_keeps_the_longer_and_stronger() {
+        let slow = applied(&[
+            StatusEffect::new(StatusEffectKind::Slow, 1.0, 0.5),
+            StatusEffect::new(StatusEffectKind::Slow, 2.0, 0.25),
+        ]);
+        assert_eq!(slow.remaining, 2.0);
+        assert_eq!(slow.magnitude, 0.5);
+        assert_eq!(slow.stacks, 1);
+    }
+
+    #[test]
+    fn test_extend_adds_the_durations() {
+        let stun = applied(&[
+            StatusEffect::new(StatusEffectKind::Stun, 0.5, 0.0),
+            StatusEffect::new(StatusEffectKind::Stun, 0.25, 0.0),
+        ]);
+        assert_eq!(stun.remaining, 0.75);
+    }
+
+    #[test]
+    fn test_intensify_stacks_up_to_the_limit() {
+        let poison = StatusEffect::new(StatusEffectKind::Poison, 3.0, 1.0);
+        let stacked = applied(&[poison.clone(), poison.clone(), poison.clone()]);
+        assert_eq!(stacked.stacks, 3);
+        assert_eq!(stacked.tick_damage(), 3);
+        let capped = applied(&vec![poison; 8]);
+        assert_eq!(capped.stacks, 5);
+    }
+
+    #[test]
+    fn test_ticks_fire_once_per_interval() {
+        let mut burn = StatusEffect::new(StatusEffectKind::Burn, 2.0, 1.0);
+        let ticks: Vec<u32> = (0..8).map(|_| burn.advance(0.25)).collect();
+        assert_eq!(ticks, [0, 1, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(burn.advance(1.5), 3);
+
+        let mut slow = StatusEffect::new(StatusEffectKind::Slow, 2.0, 0.5);
+        assert_eq!(slow.advance(1.0), 0);
+        assert_eq!(slow.remaining, 1.0);
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::app::AppExit;
+use bevy_egui::{egui, EguiContext};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    Playing,
+    GameOver,
+}
+
+pub fn game_over_screen_system(
+    egui_context: Option<ResMut<EguiContext>>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    let mut egui_context = match egui_context {
+        Some(egui_context) => egui_context,
+        None => return,
+    };
+    egui::Window::new("Game Over")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label("You have fallen.");
+            if ui.button("Quit").clicked() {
+                exit_events.send(AppExit);
+            }
+        });
+}
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(GameState::Playing).add_system_set(
+            SystemSet::on_update(GameState::GameOver).with_system(game_over_screen_system),
+        );
+    }
+}
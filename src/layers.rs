@@ -0,0 +1,79 @@
+use crate::prelude::*;
+
+// Named collision layers. Every body belongs to one layer, which decides both what it touches and
+// what it gets pushed around by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CollisionLayer {
+    Terrain,
+    Player,
+    Minion,
+    Neutral,
+    Corpse,
+}
+
+impl CollisionLayer {
+    pub const ALL: [CollisionLayer; 5] = [
+        CollisionLayer::Terrain,
+        CollisionLayer::Player,
+        CollisionLayer::Minion,
+        CollisionLayer::Neutral,
+        CollisionLayer::Corpse,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CollisionLayer::Terrain => "terrain",
+            CollisionLayer::Player => "player",
+            CollisionLayer::Minion => "minion",
+            CollisionLayer::Neutral => "neutral",
+            CollisionLayer::Corpse => "corpse",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CollisionLayer> {
+        CollisionLayer::ALL
+            .iter()
+            .find(|layer| layer.name() == name)
+            .copied()
+    }
+
+    pub fn bit(&self) -> u32 {
+        1 << *self as u32
+    }
+
+    // Layers that generate contact and intersection events with this one.
+    pub fn touches(&self) -> &'static [CollisionLayer] {
+        match self {
+            CollisionLayer::Corpse => &[CollisionLayer::Terrain],
+            _ => &CollisionLayer::ALL,
+        }
+    }
+
+    // Layers this one is solid against. Bodies that only touch still report contacts, which is
+    // how the player and minions hurt each other without shoving.
+    pub fn solid_against(&self) -> &'static [CollisionLayer] {
+        match self {
+            CollisionLayer::Terrain => &CollisionLayer::ALL,
+            CollisionLayer::Player => &[CollisionLayer::Terrain, CollisionLayer::Player],
+            CollisionLayer::Minion => &[CollisionLayer::Terrain, CollisionLayer::Minion],
+            CollisionLayer::Neutral => &[
+                CollisionLayer::Terrain,
+                CollisionLayer::Minion,
+                CollisionLayer::Neutral,
+            ],
+            CollisionLayer::Corpse => &[CollisionLayer::Terrain],
+        }
+    }
+
+    pub fn mask(layers: &[CollisionLayer]) -> u32 {
+        layers.iter().fold(0, |mask, layer| mask | layer.bit())
+    }
+
+    pub fn collision_groups(&self) -> InteractionGroups {
+        InteractionGroups::new(self.bit(), CollisionLayer::mask(self.touches()))
+    }
+
+    pub fn solver_groups(&self) -> InteractionGroups {
+        InteractionGroups::new(self.bit(), CollisionLayer::mask(self.solid_against()))
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use player::CameraRig;
+
+pub mod ai;
+pub mod animation;
+pub mod base_bundles;
+pub mod combat;
+pub mod game_state;
+pub mod layers;
+pub mod minions;
+pub mod player;
+pub mod prelude;
+pub mod projectiles;
+pub mod replay;
+pub mod sensors;
+pub mod terrain;
+pub mod timestep;
+
+pub fn setup_camera(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle {
+            orthographic_projection: OrthographicProjection {
+                far: 1000.0,
+                depth_calculation: bevy::render::camera::DepthCalculation::ZDifference,
+                scale: 0.5,
+                ..Default::default()
+            },
+            ..OrthographicCameraBundle::new_2d()
+        })
+        .insert(CameraRig::default());
+}
@@ -1,34 +1,20 @@
-use animation::AnimationPlugin;
-use bevy::prelude::*;
-use bevy_inspector_egui::{RegisterInspectable, WorldInspectorPlugin};
-use bevy_rapier2d::prelude::*;
-use combat::CombatPlugin;
-use minions::MinionsPlugin;
-use player::PlayerPlugin;
-use sensors::sync_hitboxes;
-use terrain::TerrainPlugin;
-
-mod ai;
-mod animation;
-mod base_bundles;
-mod combat;
-mod minions;
-mod player;
-mod prelude;
-mod sensors;
-mod terrain;
-
-pub fn setup_camera(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle {
-        orthographic_projection: OrthographicProjection {
-            far: 1000.0,
-            depth_calculation: bevy::render::camera::DepthCalculation::ZDifference,
-            scale: 0.5,
-            ..Default::default()
-        },
-        ..OrthographicCameraBundle::new_2d()
-    });
-}
+use bevy::{
+    app::ScheduleRunnerPlugin, prelude::*, render::options::WgpuOptions, winit::WinitPlugin,
+};
+use bevy_inspector_egui::{InspectableRegistry, WorldInspectorPlugin};
+use bevy_rapier2d::{physics::TimestepMode, prelude::*};
+use ludum_dare_50::{
+    animation::AnimationPlugin,
+    combat::CombatPlugin,
+    game_state::GameStatePlugin,
+    minions::MinionsPlugin,
+    player::PlayerPlugin,
+    projectiles::ProjectilesPlugin,
+    replay::{ReplayMode, ReplayPlugin},
+    sensors::sync_hitboxes,
+    terrain::TerrainPlugin,
+    timestep::{GameplayAppExt, GameplayLabel, TimestepPlugin},
+};
 
 fn display_rapier_events(
     mut intersection_events: EventReader<IntersectionEvent>,
@@ -44,22 +30,60 @@ fn display_rapier_events(
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_plugin(RapierRenderPlugin)
-        .add_plugin(WorldInspectorPlugin::new())
+    let args = std::env::args().collect::<Vec<_>>();
+    let replay_mode = match ReplayMode::from_args(&args) {
+        Ok(replay_mode) => replay_mode,
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let headless = replay_mode.is_headless();
+    let mut app = App::new();
+    app.insert_resource(replay_mode);
+    if headless {
+        app.insert_resource(WgpuOptions {
+            backends: None,
+            ..Default::default()
+        })
+        .add_plugins_with(DefaultPlugins, |group| group.disable::<WinitPlugin>())
+        .add_plugin(ScheduleRunnerPlugin)
+        // There's no inspector, but the plugins still register their types with it.
+        .init_resource::<InspectableRegistry>();
+    } else {
+        app.add_plugins(DefaultPlugins);
+        // Sprites, animations and controllers reload on edit in debug builds.
+        if cfg!(debug_assertions) {
+            let asset_server = app.world.get_resource::<AssetServer>().unwrap();
+            if let Err(err) = asset_server.watch_for_changes() {
+                warn!("Could not watch assets for changes: {:?}", err);
+            }
+        }
+    }
+    app.add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugin(TimestepPlugin);
+    if !headless {
+        app.add_plugin(RapierRenderPlugin)
+            .add_plugin(WorldInspectorPlugin::new())
+            .add_gameplay_system(display_rapier_events);
+        #[cfg(feature = "sprite_editor")]
+        app.add_plugin(ludum_dare_50::animation::editor::SpriteEditorPlugin);
+    }
+    app.add_plugin(ReplayPlugin)
+        .add_plugin(GameStatePlugin)
         .add_plugin(AnimationPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(MinionsPlugin)
         .add_plugin(CombatPlugin)
+        .add_plugin(ProjectilesPlugin)
         .add_plugin(TerrainPlugin)
-        .add_system(display_rapier_events)
-        .add_system(sync_hitboxes)
+        .add_gameplay_system(sync_hitboxes.after(GameplayLabel::Sample))
         .insert_resource(RapierConfiguration {
             gravity: Vector::y() * -64.0,
             scale: 32.0,
-            ..Default::default()
+            timestep_mode: TimestepMode::FixedTimestep,
+            physics_pipeline_active: false,
+            query_pipeline_active: false,
         })
         .run();
 }
@@ -2,13 +2,18 @@ use crate::prelude::*;
 
 use crate::{
     ai::powered::{PoweredFunction, PoweredTreeDef},
-    animation::component_types::{AnimationState, ParameterizedSpriteAnimationSet},
+    animation::{
+        component_types::{AnimationEvent, AnimationState, ParameterizedSpriteAnimationSet},
+        controller::Animator,
+    },
     player::PlayerStats,
+    projectiles::SpawnProjectile,
     terrain::GroundedState,
+    timestep::{FixedTime, HitStop},
 };
 
 use super::behavior_tree::{MinionThoughts, MinionTreeNodeDef};
-use super::{Minion, MinionBrain};
+use super::{Minion, MinionBrain, RangedAttack};
 
 pub fn minion_brain() -> Box<dyn PoweredFunction<World = MinionThoughts> + Send + Sync> {
     let brain_def = PoweredTreeDef::User(MinionTreeNodeDef::ResetOnHit(Box::new(
@@ -46,10 +51,38 @@ pub fn minion_brain() -> Box<dyn PoweredFunction<World = MinionThoughts> + Send
     brain_def.create_tree()
 }
 
+pub fn ranged_minion_brain() -> Box<dyn PoweredFunction<World = MinionThoughts> + Send + Sync> {
+    let brain_def = PoweredTreeDef::User(MinionTreeNodeDef::ResetOnHit(Box::new(
+        PoweredTreeDef::Selector(vec![
+            PoweredTreeDef::Sequence(vec![
+                PoweredTreeDef::User(MinionTreeNodeDef::OnTheGround),
+                PoweredTreeDef::User(MinionTreeNodeDef::PlayerVisible),
+                PoweredTreeDef::User(MinionTreeNodeDef::IsTimid),
+                PoweredTreeDef::User(MinionTreeNodeDef::LungeAway(20.0, 10.0)),
+                PoweredTreeDef::User(MinionTreeNodeDef::WaitForGround),
+                PoweredTreeDef::User(MinionTreeNodeDef::Idle(0.25)),
+            ]),
+            PoweredTreeDef::Sequence(vec![
+                PoweredTreeDef::User(MinionTreeNodeDef::OnTheGround),
+                PoweredTreeDef::User(MinionTreeNodeDef::PlayerVisible),
+                PoweredTreeDef::User(MinionTreeNodeDef::ShootAtPlayer),
+                PoweredTreeDef::User(MinionTreeNodeDef::Idle(1.5)),
+            ]),
+            PoweredTreeDef::Sequence(vec![
+                PoweredTreeDef::User(MinionTreeNodeDef::WaitForGround),
+                PoweredTreeDef::User(MinionTreeNodeDef::Idle(1.0)),
+            ]),
+        ]),
+    )));
+    brain_def.create_tree()
+}
+
+#[allow(clippy::type_complexity)]
 pub fn minion_thought_update_system(
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     query_pipeline: Res<QueryPipeline>,
     collider_query: QueryPipelineColliderComponentsQuery,
+    mut animation_events: EventReader<AnimationEvent>,
     mut minion_query: Query<(
         Entity,
         &Minion,
@@ -58,20 +91,36 @@ pub fn minion_thought_update_system(
         &GroundedState,
         &ParameterizedSpriteAnimationSet,
         &AnimationState,
+        Option<&HitStop>,
     )>,
     player_query: Query<&PlayerStats>,
     position_query: Query<&RigidBodyPositionComponent>,
 ) {
-    for (entity, minion, mut thoughts, health, grounded, animation_set, animation_state) in
-        minion_query.iter_mut()
+    let animation_events: Vec<&AnimationEvent> = animation_events.iter().collect();
+    for (
+        entity,
+        minion,
+        mut thoughts,
+        health,
+        grounded,
+        animation_set,
+        animation_state,
+        hit_stop,
+    ) in minion_query.iter_mut()
     {
-        thoughts.frame_time = time.delta_seconds();
+        thoughts.frame_time = fixed_time.entity_delta_seconds(hit_stop);
         thoughts.player_at = None;
         thoughts.idling = false;
         thoughts.on_the_ground = grounded.on_the_ground();
         thoughts.animation = animation_state.get_animation().clone();
         thoughts.animation_complete = animation_set.animation_complete(animation_state);
+        thoughts.animation_events = animation_events
+            .iter()
+            .filter(|event| event.entity == entity)
+            .map(|event| event.name.clone())
+            .collect();
         thoughts.timid = health.current_health <= minion.timidity;
+        thoughts.hit_stun = health.in_hit_stun();
         if let Ok(minion_pos) = position_query.get(entity) {
             thoughts.self_at = Vec2::new(
                 minion_pos.0.position.translation.x,
@@ -107,31 +156,72 @@ pub fn minion_thought_update_system(
 }
 
 pub fn minion_brain_system(
-    mut minion_query: Query<(Entity, &mut MinionBrain, &mut MinionThoughts)>,
+    mut minion_query: Query<(
+        &mut MinionBrain,
+        &mut MinionThoughts,
+        Option<&StatusEffects>,
+    )>,
 ) {
-    for (entity, mut minion_brain, mut minion_thoughts) in minion_query.iter_mut() {
+    for (mut minion_brain, mut minion_thoughts, status_effects) in minion_query.iter_mut() {
+        if stunned(status_effects) {
+            minion_thoughts.lunge_towards = None;
+            minion_thoughts.shoot_at = None;
+            minion_thoughts.idling = true;
+            continue;
+        }
         minion_brain.0.resume_with(99999, &mut minion_thoughts);
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn minion_impulse_system(
     mut minion_query: Query<(
         Entity,
-        &MinionThoughts,
+        &mut MinionThoughts,
+        Option<&RangedAttack>,
         &mut TextureAtlasSprite,
-        &mut AnimationState,
+        &mut Animator,
         &mut RigidBodyVelocityComponent,
         &RigidBodyMassPropsComponent,
+        Option<&StatusEffects>,
     )>,
+    mut projectile_events: EventWriter<SpawnProjectile>,
 ) {
-    for (entity, thoughts, mut sprite, mut animation_state, mut velocity, mass) in
-        minion_query.iter_mut()
+    for (
+        entity,
+        mut thoughts,
+        ranged_attack,
+        mut sprite,
+        mut animator,
+        mut velocity,
+        mass,
+        status_effects,
+    ) in minion_query.iter_mut()
     {
+        if let Some(shoot_dir) = thoughts.shoot_at.take() {
+            if let Some(ranged_attack) = ranged_attack {
+                projectile_events.send(SpawnProjectile {
+                    kind: ranged_attack.projectile.clone(),
+                    source: entity,
+                    position: thoughts.self_at,
+                    direction: shoot_dir,
+                });
+                sprite.flip_x = shoot_dir.x < 0.0;
+            }
+        }
+        animator.set_flag("idling", thoughts.idling);
         if thoughts.idling {
-            animation_state.transition_to("Idle", true);
-        } else if let Some((lunge_dir, lunge_speed, lunge_rise)) = thoughts.lunge_towards {
-            if animation_state.try_transition_to("Lunge", false) {
+            continue;
+        }
+        // The controller enters Lunge after this system runs, so the impulse is applied once.
+        if let Some((lunge_dir, lunge_speed, lunge_rise)) = thoughts.lunge_towards {
+            if animator
+                .current_state()
+                .is_some_and(|state| state != "Lunge")
+            {
+                animator.trigger("lunge");
                 let normalized = lunge_dir.normalize_or_zero();
+                let lunge_speed = lunge_speed * speed_multiplier(status_effects);
                 velocity.apply_impulse(
                     mass,
                     Vec2::new(
@@ -12,6 +12,7 @@ pub struct MinionThoughts {
     pub on_the_ground: bool,
     pub animation: String,
     pub animation_complete: bool,
+    pub animation_events: Vec<String>,
     pub timid: bool,
 }
 
@@ -31,6 +32,7 @@ pub enum MinionTreeNodeDef {
     LungeAtPlayer(f32, f32),
     LungeAway(f32, f32),
     ShootAtPlayer,
+    WaitForEvent(String),
     Idle(f32),
     ResetOnHit(Box<PoweredTreeDef<MinionTreeNodeDef>>),
 }
@@ -44,10 +46,16 @@ pub enum MinionTreeNode {
     LungeAtPlayer(f32, f32),
     LungeAway(f32, f32),
     ShootAtPlayer,
+    WaitForEvent(String),
     Idle { duration: f32, progress: f32 },
     ResetOnHit(Box<dyn PoweredFunction<World = MinionThoughts> + Send + Sync>),
 }
 
+#[allow(
+    clippy::needless_arbitrary_self_type,
+    clippy::needless_return,
+    clippy::if_same_then_else
+)]
 impl PoweredFunction for MinionTreeNode {
     type World = MinionThoughts;
 
@@ -141,6 +149,13 @@ impl PoweredFunction for MinionTreeNode {
                 }
                 return PoweredFunctionState::Failed(gas_left);
             }
+            MinionTreeNode::WaitForEvent(name) => {
+                if thoughts.animation_events.contains(name) {
+                    return PoweredFunctionState::Complete(gas_left);
+                } else {
+                    return PoweredFunctionState::Waiting(gas_left);
+                }
+            }
             MinionTreeNode::Idle { duration, progress } => {
                 *progress += thoughts.frame_time;
                 if progress < duration {
@@ -192,6 +207,9 @@ impl UserNodeDefinition for MinionTreeNodeDef {
                 Box::new(MinionTreeNode::LungeAway(*speed, *rise))
             }
             MinionTreeNodeDef::ShootAtPlayer => Box::new(MinionTreeNode::ShootAtPlayer),
+            MinionTreeNodeDef::WaitForEvent(name) => {
+                Box::new(MinionTreeNode::WaitForEvent(name.clone()))
+            }
             MinionTreeNodeDef::Idle(duration) => Box::new(MinionTreeNode::Idle {
                 duration: *duration,
                 progress: 0.0,
@@ -5,15 +5,32 @@ pub mod behavior_tree;
 use self::{ai::*, behavior_tree::MinionThoughts};
 
 use crate::{
-    ai::powered::PoweredFunction, animation::bundles::AnimatedSprite,
-    base_bundles::WorldEntityBuilder, terrain::GroundedState,
+    ai::powered::PoweredFunction,
+    animation::{bundles::AnimatedSprite, controller::Animator},
+    base_bundles::WorldEntityBuilder,
+    layers::CollisionLayer,
+    terrain::GroundedState,
+    timestep::{GameplayAppExt, GameplayLabel},
 };
 
 #[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
 pub struct Minion {
     los_distance: f32,
     timidity: i32,
-    hit_by: Vec<u32>,
+}
+
+// Minions with a ranged attack fire this projectile kind whenever their brain decides to shoot.
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct RangedAttack {
+    pub projectile: String,
+}
+
+impl RangedAttack {
+    pub fn new(projectile: &str) -> Self {
+        RangedAttack {
+            projectile: projectile.to_string(),
+        }
+    }
 }
 
 #[derive(Component)]
@@ -24,25 +41,26 @@ impl Minion {
         Minion {
             los_distance,
             timidity,
-            hit_by: Vec::new(),
         }
     }
+}
 
-    pub fn hit(&mut self, attack_id: u32) -> bool {
-        if self.hit_by.contains(&attack_id) {
-            false
-        } else {
-            self.hit_by.push(attack_id);
-            true
-        }
+fn minion_death_system(
+    mut commands: Commands,
+    minion_query: Query<Entity, (With<Minion>, Added<Dying>)>,
+) {
+    for entity in minion_query.iter() {
+        commands
+            .entity(entity)
+            .remove::<MinionBrain>()
+            .remove::<MinionThoughts>();
     }
 }
 
 fn spawn_minion(mut commands: Commands, assets: Res<AssetServer>) {
     let world_entity = WorldEntityBuilder::of_size(0.5)
         .at_position(5.0, 10.0)
-        .with_solver_group(0b0010)
-        .with_solver_filter(0b0010);
+        .in_layer(CollisionLayer::Minion);
     let mut transform = Transform::default();
     transform.translation.z = 1.0;
     commands
@@ -51,10 +69,14 @@ fn spawn_minion(mut commands: Commands, assets: Res<AssetServer>) {
             transform,
             ..Default::default()
         })
+        .insert(Animator::new(assets.load("sprites/Minion.controller")))
         .insert_bundle(world_entity.rigid_body_bundle())
         .insert_bundle(world_entity.collider_bundle())
         .insert(ContactType::Minion(1))
+        .insert(Faction::Minions)
         .insert(Health::new(3))
+        .insert(HitReaction::new(0.3, 0.2))
+        .insert(StatusEffects::default())
         .insert(Minion::new(10.0, 1))
         .insert(MinionThoughts::default())
         .insert(MinionBrain(minion_brain()))
@@ -63,16 +85,65 @@ fn spawn_minion(mut commands: Commands, assets: Res<AssetServer>) {
         .insert(Name::new("player"));
 }
 
+fn spawn_ranged_minion(mut commands: Commands, assets: Res<AssetServer>) {
+    let world_entity = WorldEntityBuilder::of_size(0.5)
+        .at_position(-8.0, 10.0)
+        .in_layer(CollisionLayer::Minion);
+    let mut transform = Transform::default();
+    transform.translation.z = 1.0;
+    commands
+        .spawn_bundle(AnimatedSprite {
+            sprite: TextureAtlasSprite {
+                color: Color::rgb(0.6, 0.8, 1.0),
+                ..Default::default()
+            },
+            sprite_animation: assets.load("sprites/Minion.sprite"),
+            transform,
+            ..Default::default()
+        })
+        .insert(Animator::new(assets.load("sprites/Minion.controller")))
+        .insert_bundle(world_entity.rigid_body_bundle())
+        .insert_bundle(world_entity.collider_bundle())
+        .insert(ContactType::Minion(1))
+        .insert(Faction::Minions)
+        .insert(Health::new(2))
+        .insert(HitReaction::new(0.3, 0.2))
+        .insert(StatusEffects::default())
+        .insert(Minion::new(12.0, 1))
+        .insert(RangedAttack::new("Bolt"))
+        .insert(MinionThoughts::default())
+        .insert(MinionBrain(ranged_minion_brain()))
+        .insert(GroundedState::new(0.5, 0.5, 0.1))
+        .insert(RigidBodyPositionSync::Discrete)
+        .insert(Name::new("ranged minion"));
+}
+
 pub struct MinionsPlugin;
 
 impl Plugin for MinionsPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(spawn_minion)
-            .add_system(minion_thought_update_system)
-            .add_system(minion_brain_system)
-            .add_system(minion_impulse_system)
+            .add_startup_system(spawn_ranged_minion)
+            .add_gameplay_system(
+                minion_thought_update_system
+                    .label(GameplayLabel::Sense)
+                    .after(GameplayLabel::Environment),
+            )
+            .add_gameplay_system(
+                minion_brain_system
+                    .label(GameplayLabel::Decide)
+                    .after(GameplayLabel::Sense),
+            )
+            .add_gameplay_system(
+                minion_impulse_system
+                    .label(GameplayLabel::Act)
+                    .after(GameplayLabel::Decide),
+            )
+            .add_gameplay_system(minion_death_system.after(GameplayLabel::Damage))
             .register_type::<Minion>()
             .register_type::<MinionThoughts>()
+            .register_type::<RangedAttack>()
+            .register_inspectable::<RangedAttack>()
             .register_inspectable::<MinionThoughts>()
             .register_inspectable::<Minion>();
     }
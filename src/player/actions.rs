@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs;
+
+use bevy::input::gamepad::{GamepadEvent, GamepadEventType};
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const ACTION_MAP_PATH: &str = "assets/config/controls.ron";
+const AXIS_DEAD_ZONE: f32 = 0.2;
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PlayerAction {
+    Move,
+    Jump,
+    Attack,
+    Dash,
+    Block,
+}
+
+impl PlayerAction {
+    pub const ALL: [PlayerAction; 5] = [
+        PlayerAction::Move,
+        PlayerAction::Jump,
+        PlayerAction::Attack,
+        PlayerAction::Dash,
+        PlayerAction::Block,
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum ActionInput {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    Axis(GamepadAxisType),
+}
+
+impl std::fmt::Display for ActionInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionInput::Key(key) => write!(f, "Key {:?}", key),
+            ActionInput::Button(button) => write!(f, "Pad {:?}", button),
+            ActionInput::Axis(axis) => write!(f, "Pad {:?}", axis),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ActionBinding {
+    pub action: PlayerAction,
+    pub input: ActionInput,
+    // Digital inputs report this value when held, analog inputs are multiplied by it.
+    pub scale: f32,
+}
+
+impl ActionBinding {
+    pub fn new(action: PlayerAction, input: ActionInput, scale: f32) -> Self {
+        ActionBinding {
+            action,
+            input,
+            scale,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActionMap {
+    pub bindings: Vec<ActionBinding>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use ActionInput::*;
+        use PlayerAction::*;
+        ActionMap {
+            bindings: vec![
+                ActionBinding::new(Move, Key(KeyCode::A), -1.0),
+                ActionBinding::new(Move, Key(KeyCode::D), 1.0),
+                ActionBinding::new(Move, Axis(GamepadAxisType::LeftStickX), 1.0),
+                ActionBinding::new(Move, Button(GamepadButtonType::DPadLeft), -1.0),
+                ActionBinding::new(Move, Button(GamepadButtonType::DPadRight), 1.0),
+                ActionBinding::new(Jump, Key(KeyCode::W), 1.0),
+                ActionBinding::new(Jump, Button(GamepadButtonType::South), 1.0),
+                ActionBinding::new(Attack, Key(KeyCode::Space), 1.0),
+                ActionBinding::new(Attack, Button(GamepadButtonType::West), 1.0),
+                ActionBinding::new(Dash, Key(KeyCode::LShift), 1.0),
+                ActionBinding::new(Dash, Button(GamepadButtonType::East), 1.0),
+                ActionBinding::new(Block, Key(KeyCode::S), 1.0),
+                ActionBinding::new(Block, Button(GamepadButtonType::RightTrigger), 1.0),
+            ],
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let bytes = fs::read(path)?;
+        Ok(ron::de::from_bytes::<ActionMap>(&bytes)?)
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let pretty = ron::ser::PrettyConfig::new();
+        fs::write(path, ron::ser::to_string_pretty(self, pretty)?)?;
+        Ok(())
+    }
+
+    pub fn bindings_for(&self, action: PlayerAction) -> impl Iterator<Item = &ActionBinding> {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.action == action)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ActionValue {
+    pub value: f32,
+    pub pressed: bool,
+    pub just_pressed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ActionState {
+    values: HashMap<PlayerAction, ActionValue>,
+}
+
+impl ActionState {
+    pub fn value(&self, action: PlayerAction) -> f32 {
+        self.values
+            .get(&action)
+            .map(|value| value.value)
+            .unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: PlayerAction) -> bool {
+        self.values
+            .get(&action)
+            .map(|value| value.pressed)
+            .unwrap_or(false)
+    }
+
+    pub fn just_pressed(&self, action: PlayerAction) -> bool {
+        self.values
+            .get(&action)
+            .map(|value| value.just_pressed)
+            .unwrap_or(false)
+    }
+
+    // Called by the gameplay tick that acted on the presses.
+    pub fn consume_just_pressed(&mut self) {
+        for value in self.values.values_mut() {
+            value.just_pressed = false;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+pub fn gamepad_connection_system(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut gamepads: ResMut<ConnectedGamepads>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        match event_type {
+            GamepadEventType::Connected if !gamepads.0.contains(gamepad) => {
+                gamepads.0.push(*gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                gamepads.0.retain(|connected| connected != gamepad);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_input(
+    input: &ActionInput,
+    keyboard_input: &Input<KeyCode>,
+    button_input: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    gamepads: &ConnectedGamepads,
+) -> f32 {
+    match input {
+        ActionInput::Key(key) => {
+            if keyboard_input.pressed(*key) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        ActionInput::Button(button) => {
+            if gamepads
+                .0
+                .iter()
+                .any(|gamepad| button_input.pressed(GamepadButton(*gamepad, *button)))
+            {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        ActionInput::Axis(axis) => gamepads
+            .0
+            .iter()
+            .filter_map(|gamepad| axes.get(GamepadAxis(*gamepad, *axis)))
+            .find(|value| value.abs() > AXIS_DEAD_ZONE)
+            .unwrap_or(0.0),
+    }
+}
+
+pub fn action_state_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    button_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<ConnectedGamepads>,
+    action_map: Res<ActionMap>,
+    mut action_state: ResMut<ActionState>,
+) {
+    for action in PlayerAction::ALL {
+        let value = action_map
+            .bindings_for(action)
+            .map(|binding| {
+                binding.scale
+                    * read_input(
+                        &binding.input,
+                        &keyboard_input,
+                        &button_input,
+                        &axes,
+                        &gamepads,
+                    )
+            })
+            .sum::<f32>()
+            .clamp(-1.0, 1.0);
+        let was_pressed = action_state.pressed(action);
+        let pressed = value != 0.0;
+        // Frames that run no gameplay tick would drop a press, so it stays latched until a tick
+        // consumes it.
+        let just_pressed = (pressed && !was_pressed) || action_state.just_pressed(action);
+        action_state.values.insert(
+            action,
+            ActionValue {
+                value,
+                pressed,
+                just_pressed,
+            },
+        );
+    }
+}
+
+pub fn load_action_map_system(mut commands: Commands) {
+    let action_map = match ActionMap::load(ACTION_MAP_PATH) {
+        Ok(action_map) => action_map,
+        Err(err) => {
+            println!(
+                "Using default controls, could not load {}: {}",
+                ACTION_MAP_PATH, err
+            );
+            ActionMap::default()
+        }
+    };
+    commands.insert_resource(action_map);
+}
+
+#[derive(Debug, Default)]
+pub struct RebindingScreen {
+    pub open: bool,
+    pub listening_for: Option<usize>,
+    pub status: Option<String>,
+}
+
+fn capture_input(
+    keyboard_input: &Input<KeyCode>,
+    button_input: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    gamepads: &ConnectedGamepads,
+) -> Option<ActionInput> {
+    if let Some(key) = keyboard_input.get_just_pressed().next() {
+        return Some(ActionInput::Key(*key));
+    }
+    if let Some(GamepadButton(_gamepad, button)) = button_input.get_just_pressed().next() {
+        return Some(ActionInput::Button(*button));
+    }
+    for gamepad in gamepads.0.iter() {
+        for axis in [
+            GamepadAxisType::LeftStickX,
+            GamepadAxisType::LeftStickY,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        ] {
+            if let Some(value) = axes.get(GamepadAxis(*gamepad, axis)) {
+                if value.abs() > 0.5 {
+                    return Some(ActionInput::Axis(axis));
+                }
+            }
+        }
+    }
+    None
+}
+
+pub fn rebinding_screen_system(
+    egui_context: Option<ResMut<EguiContext>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    button_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<ConnectedGamepads>,
+    mut action_map: ResMut<ActionMap>,
+    mut screen: ResMut<RebindingScreen>,
+) {
+    let mut egui_context = match egui_context {
+        Some(egui_context) => egui_context,
+        None => return,
+    };
+    if screen.listening_for.is_none() && keyboard_input.just_pressed(KeyCode::F1) {
+        screen.open = !screen.open;
+    }
+    if !screen.open {
+        return;
+    }
+    if let Some(idx) = screen.listening_for {
+        if let Some(input) = capture_input(&keyboard_input, &button_input, &axes, &gamepads) {
+            // Escape cancels, dropping freshly added bindings that were never assigned.
+            if input != ActionInput::Key(KeyCode::Escape) {
+                action_map.bindings[idx].input = input;
+            } else if action_map.bindings[idx].input == input {
+                action_map.bindings.remove(idx);
+            }
+            screen.listening_for = None;
+        }
+    }
+    let mut open = screen.open;
+    egui::Window::new("Controls")
+        .open(&mut open)
+        .show(egui_context.ctx_mut(), |ui| {
+            let mut remove = None;
+            for action in PlayerAction::ALL {
+                ui.heading(format!("{:?}", action));
+                for (idx, binding) in action_map.bindings.iter_mut().enumerate() {
+                    if binding.action != action {
+                        continue;
+                    }
+                    ui.horizontal(|ui| {
+                        let label = if screen.listening_for == Some(idx) {
+                            "Press an input...".to_string()
+                        } else {
+                            binding.input.to_string()
+                        };
+                        if ui.button(label).clicked() {
+                            screen.listening_for = Some(idx);
+                        }
+                        ui.add(egui::DragValue::new(&mut binding.scale).speed(0.1));
+                        if ui.button("Remove").clicked() {
+                            remove = Some(idx);
+                        }
+                    });
+                }
+                if ui.button(format!("Add {:?} binding", action)).clicked() {
+                    action_map.bindings.push(ActionBinding::new(
+                        action,
+                        ActionInput::Key(KeyCode::Escape),
+                        1.0,
+                    ));
+                    screen.listening_for = Some(action_map.bindings.len() - 1);
+                }
+                ui.separator();
+            }
+            if let Some(idx) = remove {
+                action_map.bindings.remove(idx);
+                screen.listening_for = None;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    screen.status = Some(match action_map.save(ACTION_MAP_PATH) {
+                        Ok(()) => format!("Saved to {}", ACTION_MAP_PATH),
+                        Err(err) => format!("Could not save: {}", err),
+                    });
+                }
+                if ui.button("Reset to defaults").clicked() {
+                    *action_map = ActionMap::default();
+                    screen.listening_for = None;
+                }
+            });
+            if let Some(status) = &screen.status {
+                ui.label(status);
+            }
+        });
+    screen.open = open;
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .init_resource::<ConnectedGamepads>()
+            .init_resource::<RebindingScreen>()
+            .insert_resource(ActionMap::default())
+            .add_startup_system(load_action_map_system)
+            .add_system(gamepad_connection_system)
+            .add_system(action_state_system)
+            .add_system(rebinding_screen_system);
+    }
+}
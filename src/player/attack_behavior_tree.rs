@@ -1,6 +1,9 @@
 use crate::{
     ai::powered::*,
-    animation::component_types::{AnimationState, ParameterizedSpriteAnimationSet},
+    animation::{
+        component_types::{AnimationEvent, AnimationState, ParameterizedSpriteAnimationSet},
+        controller::Animator,
+    },
     prelude::*,
     terrain::GroundedState,
 };
@@ -38,9 +41,9 @@ impl PlayerAttackType {
             PlayerAttackType::AirSlash => PoweredTreeDef::Sequence(vec![
                 PoweredTreeDef::User(AttackTreeNodeDef::GoIntangible),
                 PoweredTreeDef::User(AttackTreeNodeDef::SetDamage(2)),
-                PoweredTreeDef::User(AttackTreeNodeDef::SetFrame(8, 0.1)),
+                PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("AirSlashRise".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForFalling),
-                PoweredTreeDef::User(AttackTreeNodeDef::SetFrame(9, 0.1)),
+                PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("AirSlashFall".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForGround),
                 PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("AirSlash".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForAnimation("AirSlash".to_string())),
@@ -48,9 +51,14 @@ impl PlayerAttackType {
             PlayerAttackType::Plunge => PoweredTreeDef::Sequence(vec![
                 PoweredTreeDef::User(AttackTreeNodeDef::GoIntangible),
                 PoweredTreeDef::User(AttackTreeNodeDef::SetDamage(3)),
-                PoweredTreeDef::User(AttackTreeNodeDef::SetFrame(8, 0.1)),
+                PoweredTreeDef::User(AttackTreeNodeDef::ApplyEffectOnHit(
+                    StatusEffectKind::Stun,
+                    1.0,
+                    0.0,
+                )),
+                PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("AirSlashRise".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForFalling),
-                PoweredTreeDef::User(AttackTreeNodeDef::SetFrame(9, 0.1)),
+                PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("AirSlashFall".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForGround),
                 PoweredTreeDef::User(AttackTreeNodeDef::PlayAnimation("Plunge".to_string())),
                 PoweredTreeDef::User(AttackTreeNodeDef::WaitForAnimation("Plunge".to_string())),
@@ -60,6 +68,7 @@ impl PlayerAttackType {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for PlayerAttackType {
     fn default() -> Self {
         PlayerAttackType::Slash
@@ -70,37 +79,43 @@ impl Default for PlayerAttackType {
 pub struct AttackImpulses {
     pub attack_id: u32,
     pub attack_damage: i32,
+    pub knockback: Vec2,
     pub speed: Vec2,
     pub set_speed: Option<Vec2>,
     pub play_animation: Option<String>,
-    pub animation_frame: Option<usize>,
-    pub animation_time: f32,
     pub on_the_ground: bool,
     pub animation: String,
     pub animation_complete: bool,
+    pub animation_events: Vec<String>,
     pub hit_minions: Vec<Entity>,
     pub intangible: bool,
+    #[reflect(ignore)]
+    pub on_hit_effect: Option<StatusEffect>,
 }
 
 impl AttackImpulses {
     pub fn new(attack_id: u32) -> Self {
         AttackImpulses {
             attack_id,
+            knockback: Vec2::new(0.0, HIT_LAUNCH_SPEED),
             ..Default::default()
         }
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum AttackTreeNodeDef {
     SetDamage(i32),
+    SetKnockback(f32, f32),
+    // Kind, duration and magnitude of an effect every later hit of this attack applies.
+    ApplyEffectOnHit(StatusEffectKind, f32, f32),
     Velocity(f32, f32),
     ClearVelocity,
     PlayAnimation(String),
-    SetFrame(usize, f32),
-    ClearFrame,
-    WaitForFrame,
     WaitForAnimation(String),
+    // Waits for the current animation to reach a frame carrying the named event.
+    WaitForEvent(String),
     WaitForFalling,
     WaitForGround,
     OnTheGround,
@@ -108,6 +123,7 @@ pub enum AttackTreeNodeDef {
     GoIntangible,
 }
 
+#[allow(clippy::needless_arbitrary_self_type, unused_variables)]
 impl PoweredFunction for AttackTreeNodeDef {
     type World = AttackImpulses;
 
@@ -121,6 +137,14 @@ impl PoweredFunction for AttackTreeNodeDef {
                 attack.attack_damage = *damage;
                 PoweredFunctionState::Complete(gas_left)
             }
+            AttackTreeNodeDef::SetKnockback(x, y) => {
+                attack.knockback = Vec2::new(*x, *y);
+                PoweredFunctionState::Complete(gas_left)
+            }
+            AttackTreeNodeDef::ApplyEffectOnHit(kind, duration, magnitude) => {
+                attack.on_hit_effect = Some(StatusEffect::new(*kind, *duration, *magnitude));
+                PoweredFunctionState::Complete(gas_left)
+            }
             AttackTreeNodeDef::Velocity(x, y) => {
                 attack.set_speed = Some(Vec2::new(*x, *y));
                 PoweredFunctionState::Complete(gas_left)
@@ -129,27 +153,10 @@ impl PoweredFunction for AttackTreeNodeDef {
                 attack.set_speed = None;
                 PoweredFunctionState::Complete(gas_left)
             }
-            AttackTreeNodeDef::SetFrame(frame, time) => {
-                attack.animation_frame = Some(*frame);
-                attack.animation_time = *time;
-                PoweredFunctionState::Complete(gas_left)
-            }
             AttackTreeNodeDef::PlayAnimation(animation) => {
-                attack.animation_frame = None;
                 attack.play_animation = Some(animation.clone());
                 PoweredFunctionState::Complete(gas_left)
             }
-            AttackTreeNodeDef::WaitForFrame => {
-                if attack.animation_time <= 0.0 {
-                    PoweredFunctionState::Complete(gas_left)
-                } else {
-                    PoweredFunctionState::Waiting(gas_left)
-                }
-            }
-            AttackTreeNodeDef::ClearFrame => {
-                attack.animation_frame = None;
-                PoweredFunctionState::Complete(gas_left)
-            }
             AttackTreeNodeDef::WaitForAnimation(animation) => {
                 if attack.animation.eq(animation) {
                     attack.play_animation = None;
@@ -164,6 +171,13 @@ impl PoweredFunction for AttackTreeNodeDef {
                     PoweredFunctionState::Waiting(gas_left)
                 }
             }
+            AttackTreeNodeDef::WaitForEvent(name) => {
+                if attack.animation_events.contains(name) {
+                    PoweredFunctionState::Complete(gas_left)
+                } else {
+                    PoweredFunctionState::Waiting(gas_left)
+                }
+            }
             AttackTreeNodeDef::WaitForGround => {
                 if attack.on_the_ground {
                     PoweredFunctionState::Complete(gas_left)
@@ -213,8 +227,9 @@ impl UserNodeDefinition for AttackTreeNodeDef {
 }
 
 pub fn attack_impulse_update_system(
-    time: Res<Time>,
+    mut animation_events: EventReader<AnimationEvent>,
     mut attacker_query: Query<(
+        Entity,
         &mut AttackImpulses,
         &GroundedState,
         &ParameterizedSpriteAnimationSet,
@@ -222,11 +237,16 @@ pub fn attack_impulse_update_system(
         &RigidBodyVelocityComponent,
     )>,
 ) {
-    for (mut impulses, grounded, animation_set, animation_state, velocity) in
+    let animation_events: Vec<&AnimationEvent> = animation_events.iter().collect();
+    for (entity, mut impulses, grounded, animation_set, animation_state, velocity) in
         attacker_query.iter_mut()
     {
+        impulses.animation_events = animation_events
+            .iter()
+            .filter(|event| event.entity == entity)
+            .map(|event| event.name.clone())
+            .collect();
         impulses.on_the_ground = grounded.on_the_ground();
-        impulses.animation_time -= time.delta_seconds();
         impulses.animation = animation_state.get_animation().to_string();
         impulses.animation_complete = animation_set.animation_complete(animation_state);
         impulses.speed = velocity.linvel.into();
@@ -268,29 +288,20 @@ pub fn attack_brain_system(
 pub fn attack_impulse_system(
     mut attacker_query: Query<(
         &AttackImpulses,
-        &mut TextureAtlasSprite,
-        &mut AnimationState,
+        &mut Animator,
         &mut RigidBodyVelocityComponent,
         &mut ContactType,
     )>,
 ) {
-    for (impulses, mut sprite, mut animation_state, mut velocity, mut contact_type) in
-        attacker_query.iter_mut()
-    {
+    for (impulses, mut animator, mut velocity, mut contact_type) in attacker_query.iter_mut() {
         if impulses.intangible {
             *contact_type = ContactType::Inactive;
         }
-        if let Some(frame) = impulses.animation_frame {
-            if impulses.animation_time > 0.0 {
-                sprite.index = frame;
-                animation_state.transition_to("", false);
-            }
-        }
         if let Some(velocity_vec) = impulses.set_speed {
             velocity.linvel = velocity_vec.into();
         }
         if let Some(animation) = &impulses.play_animation {
-            animation_state.transition_to(animation, false);
+            animator.trigger(animation);
         }
     }
 }
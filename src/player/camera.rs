@@ -2,19 +2,151 @@ use crate::prelude::*;
 
 use super::PlayerStats;
 
+const PLAYER_HIT_TRAUMA: f32 = 0.5;
+const ENEMY_HIT_TRAUMA: f32 = 0.3;
+
+// Adds trauma to the camera, which decays over time. Shake strength grows with trauma squared.
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenShake(pub f32);
+
+#[derive(Component, Debug, Reflect, Inspectable, Clone)]
+pub struct CameraRig {
+    // Roughly how many times per second the camera closes the gap to its target.
+    pub smoothing: f32,
+    pub dead_zone: Vec2,
+    pub look_ahead: f32,
+    pub vertical_offset: f32,
+    pub bounds_min: Vec2,
+    pub bounds_max: Vec2,
+    pub max_shake_offset: f32,
+    pub trauma_decay: f32,
+    trauma: f32,
+    shake_time: f32,
+    focus: Vec2,
+    initialized: bool,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        CameraRig {
+            smoothing: 6.0,
+            dead_zone: Vec2::new(24.0, 32.0),
+            look_ahead: 48.0,
+            vertical_offset: 32.0,
+            bounds_min: Vec2::new(-1024.0, -96.0),
+            bounds_max: Vec2::new(1024.0, 1024.0),
+            max_shake_offset: 12.0,
+            trauma_decay: 1.5,
+            trauma: 0.0,
+            shake_time: 0.0,
+            focus: Vec2::ZERO,
+            initialized: false,
+        }
+    }
+}
+
+impl CameraRig {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    fn follow(&mut self, target: Vec2, delta_seconds: f32) {
+        if !self.initialized {
+            self.focus = target;
+            self.initialized = true;
+            return;
+        }
+        let diff = target - self.focus;
+        let excess = Vec2::new(
+            diff.x.signum() * (diff.x.abs() - self.dead_zone.x).max(0.0),
+            diff.y.signum() * (diff.y.abs() - self.dead_zone.y).max(0.0),
+        );
+        let t = 1.0 - (-self.smoothing * delta_seconds).exp();
+        self.focus += excess * t;
+    }
+
+    fn clamp_to_bounds(&mut self, half_view: Vec2) {
+        let min = self.bounds_min + half_view;
+        let max = self.bounds_max - half_view;
+        self.focus.x = if min.x > max.x {
+            (self.bounds_min.x + self.bounds_max.x) / 2.0
+        } else {
+            self.focus.x.clamp(min.x, max.x)
+        };
+        self.focus.y = if min.y > max.y {
+            (self.bounds_min.y + self.bounds_max.y) / 2.0
+        } else {
+            self.focus.y.clamp(min.y, max.y)
+        };
+    }
+
+    fn shake_offset(&mut self, delta_seconds: f32) -> Vec2 {
+        self.shake_time += delta_seconds;
+        self.trauma = (self.trauma - self.trauma_decay * delta_seconds).max(0.0);
+        let strength = self.max_shake_offset * self.trauma * self.trauma;
+        // Cheap, smooth pseudo-noise. Presentation only, so replays don't need to reproduce it.
+        let t = self.shake_time * 30.0;
+        Vec2::new(
+            (t * 1.3).sin() * 0.6 + (t * 2.9 + 1.7).sin() * 0.4,
+            (t * 1.7 + 0.5).sin() * 0.6 + (t * 3.1 + 2.3).sin() * 0.4,
+        ) * strength
+    }
+}
+
+pub fn damage_screen_shake_system(
+    mut damage_applied: EventReader<DamageApplied>,
+    mut screen_shake: EventWriter<ScreenShake>,
+    player_query: Query<&PlayerStats>,
+) {
+    for event in damage_applied
+        .iter()
+        .filter(|event| event.kind != DamageKind::Status)
+    {
+        if player_query.get(event.target).is_ok() {
+            screen_shake.send(ScreenShake(PLAYER_HIT_TRAUMA));
+        } else {
+            screen_shake.send(ScreenShake(ENEMY_HIT_TRAUMA));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn player_camera_system(
+    time: Res<Time>,
+    mut shake_events: EventReader<ScreenShake>,
     mut queries: QuerySet<(
-        QueryState<(&PlayerStats, &GlobalTransform)>,
-        QueryState<(&Camera, &mut GlobalTransform)>,
+        QueryState<(&PlayerStats, &GlobalTransform, &TextureAtlasSprite)>,
+        QueryState<(&mut CameraRig, &OrthographicProjection, &mut Transform)>,
     )>,
 ) {
-    let mut x = None;
-    for (_player, player_transform) in queries.q0().iter() {
-        x = Some(player_transform.translation.x);
+    let mut target = None;
+    for (_player, player_transform, sprite) in queries.q0().iter() {
+        target = Some((
+            Vec2::new(
+                player_transform.translation.x,
+                player_transform.translation.y,
+            ),
+            if sprite.flip_x { -1.0 } else { 1.0 },
+        ));
     }
-    if let Some(x) = x {
-        for (_camera, mut camera_transform) in queries.q1().iter_mut() {
-            camera_transform.translation.x = x;
+    let trauma: f32 = shake_events.iter().map(|ScreenShake(trauma)| trauma).sum();
+    for (mut rig, projection, mut camera_transform) in queries.q1().iter_mut() {
+        rig.add_trauma(trauma);
+        if let Some((player_at, facing)) = target {
+            let look_at = player_at + Vec2::new(facing * rig.look_ahead, rig.vertical_offset);
+            rig.follow(look_at, time.delta_seconds());
         }
+        let half_view = Vec2::new(
+            (projection.right - projection.left) / 2.0,
+            (projection.top - projection.bottom) / 2.0,
+        ) * projection.scale;
+        rig.clamp_to_bounds(half_view);
+        let offset = rig.shake_offset(time.delta_seconds());
+        camera_transform.translation.x = rig.focus.x + offset.x;
+        camera_transform.translation.y = rig.focus.y + offset.y;
     }
 }
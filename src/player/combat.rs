@@ -1,18 +1,71 @@
+#[allow(unused_imports)]
 use crate::{
     animation::hitboxes::{Hitbox, SpriteSheetHitboxes},
+    game_state::GameState,
     prelude::*,
     sensors::SensorEntity,
-    terrain::GroundedState,
 };
 
+use super::attack_behavior_tree::{AttackBrain, AttackImpulses, PlayerAttackType};
 use super::PlayerState;
 
-pub fn player_hit_stun_recovery_system(
-    mut player_query: Query<(&GroundedState, &mut PlayerState)>,
-) {
-    for (grounded_state, mut player_state) in player_query.iter_mut() {
-        if *player_state == PlayerState::HitStun && grounded_state.on_the_ground() {
+pub fn player_hit_stun_recovery_system(mut player_query: Query<(&Health, &mut PlayerState)>) {
+    for (health, mut player_state) in player_query.iter_mut() {
+        if *player_state == PlayerState::HitStun && !health.in_hit_stun() {
             *player_state = PlayerState::Controlled;
         }
     }
 }
+
+// A parried player loses the rest of their attack and is stunned like any other hit.
+pub fn player_parried_system(
+    mut commands: Commands,
+    mut blocked_events: EventReader<Blocked>,
+    mut player_query: Query<&mut PlayerState>,
+) {
+    for event in blocked_events.iter().filter(|event| event.parried) {
+        if let Ok(mut player_state) = player_query.get_mut(event.attacker) {
+            *player_state = PlayerState::HitStun;
+            commands
+                .entity(event.attacker)
+                .remove::<PlayerAttackType>()
+                .remove::<AttackBrain>()
+                .remove::<AttackImpulses>();
+        }
+    }
+}
+
+// Being stunned also cuts the current attack short.
+pub fn player_stunned_system(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &StatusEffects, &mut PlayerState), With<PlayerAttackType>>,
+) {
+    for (entity, status_effects, mut player_state) in player_query.iter_mut() {
+        if status_effects.is_stunned() {
+            *player_state = PlayerState::HitStun;
+            commands
+                .entity(entity)
+                .remove::<PlayerAttackType>()
+                .remove::<AttackBrain>()
+                .remove::<AttackImpulses>();
+        }
+    }
+}
+
+pub fn player_death_system(
+    mut commands: Commands,
+    mut game_state: ResMut<State<GameState>>,
+    mut player_query: Query<(Entity, &mut PlayerState), Added<Dying>>,
+) {
+    for (entity, mut player_state) in player_query.iter_mut() {
+        *player_state = PlayerState::Dead;
+        commands
+            .entity(entity)
+            .remove::<PlayerAttackType>()
+            .remove::<AttackBrain>()
+            .remove::<AttackImpulses>();
+        if *game_state.current() != GameState::GameOver {
+            game_state.set(GameState::GameOver).ok();
+        }
+    }
+}
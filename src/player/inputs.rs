@@ -1,74 +1,166 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-use crate::{animation::component_types::AnimationState, terrain::GroundedState};
+use crate::{
+    animation::controller::Animator,
+    replay::ReplayMode,
+    terrain::{GroundedState, WallContactState},
+    timestep::FixedTime,
+};
 
+use super::actions::{ActionState, PlayerAction};
 use super::attack_behavior_tree::PlayerAttackType;
+use super::movement::*;
 use super::{PlayerState, PlayerStats};
 
-#[derive(Default, Component, Debug, Clone, Reflect, Inspectable)]
+#[derive(Default, Component, Debug, Clone, Reflect, Inspectable, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct PlayerInputState {
-    tilt_x: f32,
-    wants_attack: bool,
-    wants_jump: bool,
+    pub tilt_x: f32,
+    pub wants_attack: bool,
+    pub wants_jump: bool,
+    #[serde(default)]
+    pub jump_pressed: bool,
+    #[serde(default)]
+    pub wants_dash: bool,
+    #[serde(default)]
+    pub wants_block: bool,
 }
 
-pub fn player_key_input_system(
-    keyboard_input: Res<Input<KeyCode>>,
+pub fn player_action_input_system(
+    mut action_state: ResMut<ActionState>,
+    fixed_time: Res<FixedTime>,
+    replay_mode: Res<ReplayMode>,
     mut query: Query<&mut PlayerInputState>,
 ) {
+    if replay_mode.is_playing() {
+        return;
+    }
+    if !fixed_time.running() {
+        return;
+    }
     for mut player in query.iter_mut() {
-        if keyboard_input.pressed(KeyCode::A) {
-            player.tilt_x = -1.0;
-        } else if keyboard_input.pressed(KeyCode::D) {
-            player.tilt_x = 1.0;
-        } else {
-            player.tilt_x = 0.0;
-        }
-        if keyboard_input.pressed(KeyCode::W) {
-            player.wants_jump = true;
-        } else {
-            player.wants_jump = false;
-        }
-        if keyboard_input.just_pressed(KeyCode::Space) {
-            player.wants_attack = true;
-        } else {
-            player.wants_attack = false;
-        }
+        player.tilt_x = action_state.value(PlayerAction::Move);
+        player.wants_jump = action_state.pressed(PlayerAction::Jump);
+        player.wants_attack = action_state.just_pressed(PlayerAction::Attack);
+        player.jump_pressed = action_state.just_pressed(PlayerAction::Jump);
+        player.wants_dash = action_state.just_pressed(PlayerAction::Dash);
+        player.wants_block = action_state.pressed(PlayerAction::Block);
     }
+    action_state.consume_just_pressed();
 }
 
+#[allow(clippy::type_complexity, clippy::if_same_then_else)]
 pub fn player_movement_system(
     mut commands: Commands,
+    fixed_time: Res<FixedTime>,
     mut query: Query<(
         Entity,
         &PlayerStats,
-        &PlayerState,
+        &MovementAbilities,
+        &mut PlayerState,
         &PlayerInputState,
+        &mut MovementState,
         &mut GroundedState,
+        &WallContactState,
         &mut TextureAtlasSprite,
-        &mut AnimationState,
+        &mut Animator,
         &mut RigidBodyVelocityComponent,
+        &mut ContactType,
+        Option<&StatusEffects>,
     )>,
 ) {
-    for (entity, stats, state, input, mut grounded, mut sprite, mut animation, mut velocity) in
-        query.iter_mut()
+    for (
+        entity,
+        stats,
+        abilities,
+        mut state,
+        input,
+        mut movement,
+        mut grounded,
+        wall_contact,
+        mut sprite,
+        mut animator,
+        mut velocity,
+        mut contact_type,
+        status_effects,
+    ) in query.iter_mut()
     {
-        if *state != PlayerState::Controlled {
+        let stats = &stats.slowed(speed_multiplier(status_effects));
+        let no_input = PlayerInputState::default();
+        let input = if stunned(status_effects) {
+            &no_input
+        } else {
+            input
+        };
+        movement.tick(fixed_time.delta_seconds(), grounded.on_the_ground());
+        match *state {
+            PlayerState::Dashing => {
+                update_dash(stats, &mut state, &movement, &mut velocity);
+                continue;
+            }
+            PlayerState::WallSliding => {
+                update_wall_slide(
+                    stats,
+                    input,
+                    wall_contact,
+                    grounded.on_the_ground(),
+                    &mut state,
+                    &mut movement,
+                    &mut sprite,
+                    &mut animator,
+                    &mut velocity,
+                );
+                continue;
+            }
+            PlayerState::WallJumping => {
+                update_wall_jump(&mut state, &movement);
+                continue;
+            }
+            PlayerState::Blocking => {
+                let still_blocking =
+                    update_block(input, grounded.on_the_ground(), &mut state, &mut velocity);
+                if still_blocking {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        if !state.is_free() {
+            continue;
+        }
+        let facing = if sprite.flip_x { -1.0 } else { 1.0 };
+        if try_start_dash(
+            stats,
+            abilities,
+            input,
+            grounded.on_the_ground(),
+            facing,
+            &mut state,
+            &mut movement,
+            &mut velocity,
+            &mut contact_type,
+        ) {
             continue;
         }
         if grounded.on_the_ground() {
+            *state = PlayerState::Controlled;
+            if update_block(input, true, &mut state, &mut velocity) {
+                continue;
+            }
             if input.tilt_x != 0.0 {
                 velocity.linvel.x = stats.walk_speed * input.tilt_x;
-                animation.transition_to("Walk", true);
                 sprite.flip_x = input.tilt_x < 0.0;
             } else {
                 velocity.linvel.x = 0.0;
-                animation.transition_to("Idle", true);
             }
-            if input.wants_jump {
+            if input.wants_jump && movement.can_jump() {
                 velocity.linvel.y = stats.jump_speed;
                 grounded.lift_off();
+                movement.jump_cooldown = stats.jump_delay;
+                *state = PlayerState::Jumping;
+                animator.trigger("jump");
             } else if input.wants_attack {
                 if input.tilt_x == 0.0 {
                     commands.entity(entity).insert(PlayerAttackType::Slash);
@@ -79,6 +171,18 @@ pub fn player_movement_system(
                 }
             }
         } else {
+            update_airborne_jump(
+                stats,
+                abilities,
+                input,
+                &mut state,
+                &mut movement,
+                &mut animator,
+                &mut velocity,
+            );
+            if try_start_wall_slide(abilities, input, wall_contact, &mut state, &velocity) {
+                continue;
+            }
             if input.tilt_x != 0.0 {
                 let desired_x_vel = stats.air_speed * input.tilt_x;
                 if desired_x_vel > 0.0 && velocity.linvel.x < desired_x_vel {
@@ -1,26 +1,40 @@
+pub mod actions;
 mod attack_behavior_tree;
 mod camera;
 mod combat;
 mod inputs;
+mod movement;
 
 use crate::prelude::*;
 
-use crate::animation::bundles::AnimatedSprite;
+use crate::animation::{bundles::AnimatedSprite, controller::Animator};
 use crate::base_bundles::WorldEntityBuilder;
+use crate::layers::CollisionLayer;
+#[allow(unused_imports)]
 use crate::sensors::SensorEntity;
 use crate::setup_camera;
-use crate::terrain::GroundedState;
+use crate::terrain::{GroundedState, WallContactState};
+use crate::timestep::{GameplayAppExt, GameplayLabel};
 
+use self::actions::ActionsPlugin;
 use self::attack_behavior_tree::attack_brain_system;
 use self::attack_behavior_tree::attack_impulse_system;
 use self::attack_behavior_tree::attack_impulse_update_system;
 pub use self::attack_behavior_tree::AttackImpulses;
 use self::attack_behavior_tree::PlayerAttackType;
+use self::camera::damage_screen_shake_system;
 use self::camera::player_camera_system;
+pub use self::camera::{CameraRig, ScreenShake};
+use self::combat::player_death_system;
 use self::combat::player_hit_stun_recovery_system;
-use self::inputs::player_key_input_system;
+use self::combat::player_parried_system;
+use self::combat::player_stunned_system;
+use self::inputs::player_action_input_system;
 use self::inputs::player_movement_system;
-use self::inputs::PlayerInputState;
+pub use self::inputs::PlayerInputState;
+use self::movement::dash_exit_system;
+use self::movement::player_animation_system;
+pub use self::movement::{MovementAbilities, MovementState};
 
 #[derive(Component, Debug, Reflect, Inspectable, Default, Copy, Clone)]
 pub struct PlayerStats {
@@ -28,20 +42,46 @@ pub struct PlayerStats {
     pub air_speed: f32,
     pub jump_speed: f32,
     pub jump_delay: f32,
+    pub jump_cut_speed: f32,
+    pub double_jump_speed: f32,
+    pub wall_slide_speed: f32,
+    pub wall_jump_speed: Vec2,
+    pub wall_jump_lock: f32,
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    pub dash_cooldown: f32,
+}
+
+impl PlayerStats {
+    // Slows ground, air and dash speed. Jumps keep their height.
+    pub fn slowed(&self, speed_multiplier: f32) -> Self {
+        PlayerStats {
+            walk_speed: self.walk_speed * speed_multiplier,
+            air_speed: self.air_speed * speed_multiplier,
+            dash_speed: self.dash_speed * speed_multiplier,
+            ..*self
+        }
+    }
 }
 
 #[derive(Component, Debug, Reflect, Inspectable, Copy, Clone, PartialEq, Eq)]
 pub enum PlayerState {
     Controlled,
+    Jumping,
+    DoubleJumping,
+    WallSliding,
+    WallJumping,
+    Dashing,
     Attacking,
+    Blocking,
     HitStun,
+    Dead,
 }
 
 fn spawn_player(mut commands: Commands, assets: Res<AssetServer>) {
     let world_entity = WorldEntityBuilder::of_size(1.0)
         .at_position(0.0, 10.0)
-        .with_solver_group(0b0001)
-        .with_solver_filter(0b0001);
+        .in_layer(CollisionLayer::Player);
     let mut transform = Transform::default();
     transform.translation.z = 1.0;
     commands
@@ -50,10 +90,15 @@ fn spawn_player(mut commands: Commands, assets: Res<AssetServer>) {
             transform,
             ..Default::default()
         })
+        .insert(Animator::new(assets.load("sprites/Player.controller")))
         .insert_bundle(world_entity.rigid_body_bundle())
         .insert_bundle(world_entity.collider_bundle())
         .insert(ContactType::Player)
+        .insert(Faction::Player)
         .insert(Health::new(20))
+        .insert(HitReaction::new(0.4, 1.0))
+        .insert(Guard::new(0.15, 0.6))
+        .insert(StatusEffects::default())
         .insert(PlayerInputState::default())
         .insert(PlayerState::Controlled)
         .insert(PlayerStats {
@@ -61,8 +106,19 @@ fn spawn_player(mut commands: Commands, assets: Res<AssetServer>) {
             air_speed: 4.0,
             jump_speed: 30.0,
             jump_delay: 0.15,
+            jump_cut_speed: 10.0,
+            double_jump_speed: 25.0,
+            wall_slide_speed: 4.0,
+            wall_jump_speed: Vec2::new(12.0, 28.0),
+            wall_jump_lock: 0.2,
+            dash_speed: 24.0,
+            dash_duration: 0.2,
+            dash_cooldown: 0.5,
         })
+        .insert(MovementAbilities::all())
+        .insert(MovementState::default())
         .insert(GroundedState::new(1.0, 1.0, 0.1))
+        .insert(WallContactState::new(1.0, 1.0, 0.1))
         .insert(RigidBodyPositionSync::Discrete)
         .insert(Name::new("player"));
 }
@@ -71,17 +127,59 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(player_camera_system)
-            .add_system(player_movement_system)
-            .add_system(player_key_input_system)
-            .add_system(player_hit_stun_recovery_system)
-            .add_system(attack_impulse_update_system)
-            .add_system(attack_brain_system)
-            .add_system(attack_impulse_system)
+        app.add_plugin(ActionsPlugin)
+            .add_event::<ScreenShake>()
+            .add_gameplay_system(damage_screen_shake_system.after(GameplayLabel::Damage))
+            .add_gameplay_system(player_death_system.after(GameplayLabel::Damage))
+            .add_gameplay_system(player_parried_system.after(GameplayLabel::Resolve))
+            .add_gameplay_system(player_stunned_system.after(GameplayLabel::Damage))
+            .add_system(player_camera_system)
+            .add_gameplay_system(
+                player_movement_system
+                    .label(GameplayLabel::Act)
+                    .after(GameplayLabel::Decide),
+            )
+            .add_gameplay_system(
+                dash_exit_system
+                    .after(GameplayLabel::Act)
+                    .before(GameplayLabel::Resolve),
+            )
+            .add_gameplay_system(
+                player_animation_system
+                    .after(GameplayLabel::Act)
+                    .before(GameplayLabel::Animate),
+            )
+            .add_gameplay_system(player_action_input_system.label(GameplayLabel::Input))
+            .add_gameplay_system(
+                player_hit_stun_recovery_system
+                    .label(GameplayLabel::Sense)
+                    .after(GameplayLabel::Environment),
+            )
+            .add_gameplay_system(
+                attack_impulse_update_system
+                    .label(GameplayLabel::Sense)
+                    .after(GameplayLabel::Environment),
+            )
+            .add_gameplay_system(
+                attack_brain_system
+                    .label(GameplayLabel::Decide)
+                    .after(GameplayLabel::Sense),
+            )
+            .add_gameplay_system(
+                attack_impulse_system
+                    .label(GameplayLabel::Act)
+                    .after(GameplayLabel::Decide),
+            )
             .add_startup_system(setup_camera)
             .add_startup_system(spawn_player)
+            .register_type::<CameraRig>()
+            .register_inspectable::<CameraRig>()
             .register_type::<PlayerStats>()
             .register_inspectable::<PlayerStats>()
+            .register_type::<MovementAbilities>()
+            .register_inspectable::<MovementAbilities>()
+            .register_type::<MovementState>()
+            .register_inspectable::<MovementState>()
             .register_type::<AttackImpulses>()
             .register_type::<PlayerAttackType>()
             .register_inspectable::<PlayerAttackType>()
@@ -0,0 +1,256 @@
+use crate::prelude::*;
+
+use crate::{
+    animation::controller::Animator,
+    terrain::{GroundedState, WallContactState},
+};
+
+use super::inputs::PlayerInputState;
+use super::{PlayerState, PlayerStats};
+
+#[derive(Component, Debug, Reflect, Inspectable, Default, Copy, Clone)]
+pub struct MovementAbilities {
+    pub variable_jump: bool,
+    pub double_jump: bool,
+    pub wall_jump: bool,
+    pub dash: bool,
+}
+
+impl MovementAbilities {
+    pub fn all() -> Self {
+        MovementAbilities {
+            variable_jump: true,
+            double_jump: true,
+            wall_jump: true,
+            dash: true,
+        }
+    }
+}
+
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct MovementState {
+    pub jump_cooldown: f32,
+    pub air_jump_used: bool,
+    pub wall_jump_time: f32,
+    pub dash_time: f32,
+    pub dash_cooldown: f32,
+    pub air_dash_used: bool,
+    pub dash_dir: f32,
+    // Set while a dash has the player's contact turned off, until dash_exit_system turns it back on.
+    pub dash_intangible: bool,
+}
+
+impl MovementState {
+    pub fn tick(&mut self, delta_seconds: f32, on_the_ground: bool) {
+        self.jump_cooldown -= delta_seconds;
+        self.wall_jump_time -= delta_seconds;
+        self.dash_time -= delta_seconds;
+        self.dash_cooldown -= delta_seconds;
+        if on_the_ground {
+            self.air_jump_used = false;
+            self.air_dash_used = false;
+        }
+    }
+
+    pub fn can_jump(&self) -> bool {
+        self.jump_cooldown <= 0.0
+    }
+}
+
+impl PlayerState {
+    // States in which the player walks, steers in the air and starts attacks as normal.
+    pub fn is_free(&self) -> bool {
+        matches!(
+            self,
+            PlayerState::Controlled | PlayerState::Jumping | PlayerState::DoubleJumping
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_start_dash(
+    stats: &PlayerStats,
+    abilities: &MovementAbilities,
+    input: &PlayerInputState,
+    on_the_ground: bool,
+    facing: f32,
+    state: &mut PlayerState,
+    movement: &mut MovementState,
+    velocity: &mut RigidBodyVelocityComponent,
+    contact_type: &mut ContactType,
+) -> bool {
+    if !abilities.dash || !input.wants_dash || movement.dash_cooldown > 0.0 {
+        return false;
+    }
+    if !on_the_ground && movement.air_dash_used {
+        return false;
+    }
+    let dash_dir = if input.tilt_x != 0.0 {
+        input.tilt_x.signum()
+    } else {
+        facing
+    };
+    *state = PlayerState::Dashing;
+    movement.dash_time = stats.dash_duration;
+    movement.dash_cooldown = stats.dash_duration + stats.dash_cooldown;
+    movement.air_dash_used = !on_the_ground;
+    movement.dash_dir = dash_dir;
+    velocity.linvel = Vec2::new(dash_dir * stats.dash_speed, 0.0).into();
+    movement.dash_intangible = true;
+    *contact_type = ContactType::Inactive;
+    true
+}
+
+pub fn update_dash(
+    stats: &PlayerStats,
+    state: &mut PlayerState,
+    movement: &MovementState,
+    velocity: &mut RigidBodyVelocityComponent,
+) {
+    if movement.dash_time > 0.0 {
+        velocity.linvel = Vec2::new(movement.dash_dir * stats.dash_speed, 0.0).into();
+    } else {
+        velocity.linvel.x = movement.dash_dir * stats.air_speed;
+        *state = PlayerState::Controlled;
+    }
+}
+
+// Whatever ends a dash, running out or a hit knocking the player out of it, gives the contact
+// back. A dying player keeps the contact death_system turned off.
+pub fn dash_exit_system(
+    mut query: Query<(&PlayerState, &mut MovementState, &mut ContactType), Without<Dying>>,
+) {
+    for (state, mut movement, mut contact_type) in query.iter_mut() {
+        if movement.dash_intangible && *state != PlayerState::Dashing {
+            movement.dash_intangible = false;
+            *contact_type = ContactType::Player;
+        }
+    }
+}
+
+pub fn update_airborne_jump(
+    stats: &PlayerStats,
+    abilities: &MovementAbilities,
+    input: &PlayerInputState,
+    state: &mut PlayerState,
+    movement: &mut MovementState,
+    animator: &mut Animator,
+    velocity: &mut RigidBodyVelocityComponent,
+) {
+    if abilities.variable_jump
+        && *state != PlayerState::Controlled
+        && !input.wants_jump
+        && velocity.linvel.y > stats.jump_cut_speed
+    {
+        velocity.linvel.y = stats.jump_cut_speed;
+    }
+    if abilities.double_jump && input.jump_pressed && !movement.air_jump_used && movement.can_jump()
+    {
+        velocity.linvel.y = stats.double_jump_speed;
+        movement.air_jump_used = true;
+        movement.jump_cooldown = stats.jump_delay;
+        *state = PlayerState::DoubleJumping;
+        animator.trigger("double_jump");
+    } else if *state != PlayerState::Controlled && velocity.linvel.y <= 0.0 {
+        *state = PlayerState::Controlled;
+    }
+}
+
+pub fn try_start_wall_slide(
+    abilities: &MovementAbilities,
+    input: &PlayerInputState,
+    wall_contact: &WallContactState,
+    state: &mut PlayerState,
+    velocity: &RigidBodyVelocityComponent,
+) -> bool {
+    if abilities.wall_jump
+        && wall_contact.touching_wall()
+        && velocity.linvel.y <= 0.0
+        && input.tilt_x * wall_contact.wall_dir() > 0.0
+    {
+        *state = PlayerState::WallSliding;
+        true
+    } else {
+        false
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_wall_slide(
+    stats: &PlayerStats,
+    input: &PlayerInputState,
+    wall_contact: &WallContactState,
+    on_the_ground: bool,
+    state: &mut PlayerState,
+    movement: &mut MovementState,
+    sprite: &mut TextureAtlasSprite,
+    animator: &mut Animator,
+    velocity: &mut RigidBodyVelocityComponent,
+) {
+    if on_the_ground
+        || !wall_contact.touching_wall()
+        || input.tilt_x * wall_contact.wall_dir() < 0.0
+    {
+        *state = PlayerState::Controlled;
+    } else if input.jump_pressed && movement.can_jump() {
+        let away = -wall_contact.wall_dir();
+        velocity.linvel = Vec2::new(away * stats.wall_jump_speed.x, stats.wall_jump_speed.y).into();
+        movement.wall_jump_time = stats.wall_jump_lock;
+        movement.jump_cooldown = stats.jump_delay;
+        movement.air_jump_used = false;
+        sprite.flip_x = away < 0.0;
+        *state = PlayerState::WallJumping;
+        animator.trigger("wall_jump");
+    } else {
+        sprite.flip_x = wall_contact.wall_dir() > 0.0;
+        if velocity.linvel.y < -stats.wall_slide_speed {
+            velocity.linvel.y = -stats.wall_slide_speed;
+        }
+    }
+}
+
+// The guard only goes up on the ground and holds the player in place. Returns whether the player
+// is still blocking.
+pub fn update_block(
+    input: &PlayerInputState,
+    on_the_ground: bool,
+    state: &mut PlayerState,
+    velocity: &mut RigidBodyVelocityComponent,
+) -> bool {
+    if input.wants_block && on_the_ground {
+        *state = PlayerState::Blocking;
+        velocity.linvel.x = 0.0;
+        true
+    } else {
+        *state = PlayerState::Controlled;
+        false
+    }
+}
+
+pub fn update_wall_jump(state: &mut PlayerState, movement: &MovementState) {
+    // Steering is locked for a moment so the player actually leaves the wall.
+    if movement.wall_jump_time <= 0.0 {
+        *state = PlayerState::Jumping;
+    }
+}
+
+// Dashing and wall sliding show for as long as the state lasts, walking and idling only while the
+// player is free on the ground. Jumps are one-off triggers pulled where they happen.
+pub fn player_animation_system(
+    mut query: Query<(
+        &PlayerState,
+        &GroundedState,
+        &RigidBodyVelocityComponent,
+        &mut Animator,
+    )>,
+) {
+    for (state, grounded, velocity, mut animator) in query.iter_mut() {
+        animator.set_value("speed", velocity.linvel.x.abs());
+        animator.set_flag("grounded", grounded.on_the_ground());
+        animator.set_flag("free", state.is_free());
+        animator.set_flag("attacking", *state == PlayerState::Attacking);
+        animator.set_flag("dashing", *state == PlayerState::Dashing);
+        animator.set_flag("wall_sliding", *state == PlayerState::WallSliding);
+        animator.set_flag("blocking", *state == PlayerState::Blocking);
+    }
+}
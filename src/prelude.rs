@@ -1,4 +1,5 @@
 pub use crate::combat::*;
+#[allow(ambiguous_glob_reexports)]
 pub use bevy::prelude::*;
 pub use bevy_inspector_egui::Inspectable;
 pub use bevy_inspector_egui::RegisterInspectable;
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectileKind {
+    pub speed: f32,
+    pub gravity_scale: f32,
+    pub lifetime: f32,
+    pub damage: i32,
+    pub piercing: bool,
+    pub radius: f32,
+    pub sprite: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, TypeUuid)]
+#[uuid = "6b0c1b3e-2f0e-4c83-9d53-4f1c3a6f2a71"]
+pub struct ProjectileKinds {
+    kinds: HashMap<String, ProjectileKind>,
+}
+
+impl ProjectileKinds {
+    pub fn get_kind(&self, name: &str) -> Option<&ProjectileKind> {
+        self.kinds.get(name)
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = (&String, &ProjectileKind)> {
+        self.kinds.iter()
+    }
+}
+
+#[derive(Default)]
+pub struct ProjectileKindsLoader;
+
+impl AssetLoader for ProjectileKindsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let projectile_kinds = ron::de::from_bytes::<ProjectileKinds>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(projectile_kinds));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["projectiles"]
+    }
+}
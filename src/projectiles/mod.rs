@@ -0,0 +1,262 @@
+mod kinds;
+
+pub use self::kinds::*;
+
+use crate::prelude::*;
+
+use crate::{
+    animation::{
+        bundles::AnimatedSprite,
+        hitboxes::{Hitbox, HitboxType, SpriteSheetHitboxes},
+    },
+    timestep::{FixedTime, GameplayAppExt, GameplayLabel, PreloadedAssets},
+};
+
+pub const PROJECTILE_KINDS_PATH: &str = "projectiles/default.projectiles";
+
+pub struct ProjectileLibrary {
+    pub kinds: Handle<ProjectileKinds>,
+}
+
+// Sent by anything that wants to fire. The kind is looked up in the ProjectileLibrary.
+#[derive(Debug, Clone)]
+pub struct SpawnProjectile {
+    pub kind: String,
+    pub source: Entity,
+    pub position: Vec2,
+    pub direction: Vec2,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    pub source: Entity,
+    pub faction: Option<Faction>,
+    pub damage: i32,
+    pub piercing: bool,
+    pub gravity_scale: f32,
+    pub lifetime: f32,
+    pub hit: Vec<Entity>,
+    pub spent: bool,
+}
+
+fn projectile_collider_bundle(kind: &ProjectileKind) -> ColliderBundle {
+    ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::ball(kind.radius).into(),
+        mass_properties: MassProperties::new(point![0.0, 0.0], 0.0, 0.0).into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            active_collision_types: ActiveCollisionTypes::default()
+                | ActiveCollisionTypes::KINEMATIC_STATIC,
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    }
+}
+
+fn projectile_rigid_body_bundle(position: Vec2, velocity: Vec2) -> RigidBodyBundle {
+    RigidBodyBundle {
+        body_type: RigidBodyType::KinematicVelocityBased.into(),
+        position: position.into(),
+        velocity: RigidBodyVelocity {
+            linvel: velocity.into(),
+            angvel: 0.0,
+        }
+        .into(),
+        ..Default::default()
+    }
+}
+
+pub fn load_projectile_kinds_system(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut preloaded: ResMut<PreloadedAssets>,
+) {
+    let kinds = assets.load(PROJECTILE_KINDS_PATH);
+    preloaded.add(kinds.clone_untyped());
+    commands.insert_resource(ProjectileLibrary { kinds });
+}
+
+pub fn projectile_spawn_system(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnProjectile>,
+    library: Res<ProjectileLibrary>,
+    projectile_kinds: Res<Assets<ProjectileKinds>>,
+    assets: Res<AssetServer>,
+    faction_query: Query<&Faction>,
+) {
+    let projectile_kinds = match projectile_kinds.get(&library.kinds) {
+        Some(projectile_kinds) => projectile_kinds,
+        None => return,
+    };
+    for event in spawn_events.iter() {
+        if let Some(kind) = projectile_kinds.get_kind(&event.kind) {
+            let velocity = event.direction.normalize_or_zero() * kind.speed;
+            let mut transform = Transform::default();
+            transform.translation.z = 1.5;
+            commands
+                .spawn_bundle(AnimatedSprite {
+                    sprite: TextureAtlasSprite {
+                        flip_x: velocity.x < 0.0,
+                        ..Default::default()
+                    },
+                    sprite_animation: assets.load(kind.sprite.as_str()),
+                    transform,
+                    ..Default::default()
+                })
+                .insert_bundle(projectile_rigid_body_bundle(event.position, velocity))
+                .insert_bundle(projectile_collider_bundle(kind))
+                .insert(ContactType::MinionProjectile(kind.damage))
+                .insert(Projectile {
+                    source: event.source,
+                    faction: faction_query.get(event.source).ok().copied(),
+                    damage: kind.damage,
+                    piercing: kind.piercing,
+                    gravity_scale: kind.gravity_scale,
+                    lifetime: kind.lifetime,
+                    hit: Vec::new(),
+                    spent: false,
+                })
+                .insert(RigidBodyPositionSync::Discrete)
+                .insert(Name::new(format!("projectile {}", event.kind)));
+        } else {
+            println!("Unknown projectile kind {}", event.kind);
+        }
+    }
+}
+
+pub fn projectile_motion_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    configuration: Res<RapierConfiguration>,
+    mut projectile_query: Query<(Entity, &mut Projectile, &mut RigidBodyVelocityComponent)>,
+) {
+    for (entity, mut projectile, mut velocity) in projectile_query.iter_mut() {
+        // Projectiles are kinematic, so gravity is applied here rather than by the solver.
+        velocity.linvel +=
+            configuration.gravity * projectile.gravity_scale * fixed_time.delta_seconds();
+        projectile.lifetime -= fixed_time.delta_seconds();
+        if projectile.lifetime <= 0.0 && !projectile.spent {
+            projectile.spent = true;
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub const PROJECTILE_HIT: CollisionHandler = "projectile_hit";
+
+// The first collider is the projectile, the second whatever it flew into.
+pub fn projectile_hit_system(
+    mut commands: Commands,
+    mut collisions: EventReader<RoutedCollision>,
+    mut projectile_query: Query<(&mut Projectile, &RigidBodyVelocityComponent)>,
+    target_query: Query<Option<&Faction>, With<Health>>,
+    hitbox_query: Query<(&Hitbox, &Parent)>,
+    sheet_query: Query<Option<&SpriteSheetHitboxes>>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for collision in collisions
+        .iter()
+        .filter(|collision| collision.is_for(PROJECTILE_HIT) && collision.started)
+    {
+        if let Ok((mut projectile, velocity)) = projectile_query.get_mut(collision.first) {
+            if projectile.spent {
+                continue;
+            }
+            match collision.second_category {
+                CollisionCategory::Terrain => projectile.spent = true,
+                // guard_system sends the Blocked event.
+                CollisionCategory::Hitbox(HitboxType::Block) => projectile.spent = true,
+                _ => {
+                    if let Some(target) =
+                        get_hurt_entity(collision.second, &hitbox_query, &sheet_query).filter(
+                            |target| {
+                                *target != projectile.source
+                                    && target_query.get(*target).is_ok_and(|faction| {
+                                        are_hostile(projectile.faction.as_ref(), faction)
+                                    })
+                            },
+                        )
+                    {
+                        if projectile.hit.contains(&target) {
+                            continue;
+                        }
+                        projectile.hit.push(target);
+                        let direction = if velocity.linvel.x < 0.0 { -1.0 } else { 1.0 };
+                        damage_events.send(DamageEvent {
+                            source: projectile.source,
+                            target,
+                            amount: projectile.damage,
+                            knockback: Vec2::new(direction * HIT_LAUNCH_SPEED, HIT_LAUNCH_SPEED),
+                            kind: DamageKind::Projectile,
+                            attack_id: None,
+                            effect: None,
+                            collider: Some(collision.first),
+                        });
+                        projectile.spent = !projectile.piercing;
+                    }
+                }
+            }
+            if projectile.spent {
+                commands.entity(collision.first).despawn_recursive();
+            }
+        }
+    }
+}
+
+pub struct ProjectilesPlugin;
+
+impl Plugin for ProjectilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ProjectileKinds>()
+            .add_collision_route(
+                CollisionCategory::MinionProjectile,
+                CollisionCategory::Terrain,
+                ContactKind::Intersection,
+                PROJECTILE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::MinionProjectile,
+                CollisionCategory::Hitbox(HitboxType::Block),
+                ContactKind::Intersection,
+                PROJECTILE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::MinionProjectile,
+                CollisionCategory::Hitbox(HitboxType::Hurt),
+                ContactKind::Intersection,
+                PROJECTILE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::MinionProjectile,
+                CollisionCategory::Player,
+                ContactKind::Intersection,
+                PROJECTILE_HIT,
+            )
+            .add_collision_route(
+                CollisionCategory::MinionProjectile,
+                CollisionCategory::Minion,
+                ContactKind::Intersection,
+                PROJECTILE_HIT,
+            )
+            .init_asset_loader::<ProjectileKindsLoader>()
+            .add_gameplay_event::<SpawnProjectile>()
+            .add_startup_system(load_projectile_kinds_system)
+            .add_gameplay_system(
+                projectile_spawn_system
+                    .label(GameplayLabel::Resolve)
+                    .after(GameplayLabel::Act),
+            )
+            .add_gameplay_system(
+                projectile_motion_system
+                    .label(GameplayLabel::Act)
+                    .after(GameplayLabel::Decide),
+            )
+            .add_gameplay_system(
+                projectile_hit_system
+                    .label(GameplayLabel::Resolve)
+                    .after(GameplayLabel::Act),
+            );
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::app::{AppExit, Events};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    player::PlayerInputState,
+    prelude::*,
+    timestep::{FixedTime, GameRng, GameplayAppExt, GameplayLabel, FIXED_TIMESTEP},
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub timestep: f32,
+    pub inputs: Vec<PlayerInputState>,
+    pub final_hash: Option<u64>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Replay {
+            seed,
+            timestep: FIXED_TIMESTEP,
+            ..Default::default()
+        }
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let bytes = fs::read(path)?;
+        let replay = ron::de::from_bytes::<Replay>(&bytes)?;
+        if replay.timestep != FIXED_TIMESTEP {
+            anyhow::bail!(
+                "{} was recorded at a timestep of {}, expected {}",
+                path,
+                replay.timestep,
+                FIXED_TIMESTEP
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        fs::write(path, ron::ser::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+pub enum ReplayMode {
+    Live,
+    Record { path: String, replay: Replay },
+    Playback(Replay),
+    Verify(Replay),
+}
+
+impl ReplayMode {
+    pub fn from_args(args: &[String]) -> anyhow::Result<Self> {
+        let path = args.get(2).cloned();
+        match (args.get(1).map(String::as_str), path) {
+            (Some("--record"), Some(path)) => {
+                let seed = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+                Ok(ReplayMode::Record {
+                    path,
+                    replay: Replay::new(seed),
+                })
+            }
+            (Some("--replay"), Some(path)) => Ok(ReplayMode::Playback(Replay::load(&path)?)),
+            (Some("--verify"), Some(path)) => Ok(ReplayMode::Verify(Replay::load(&path)?)),
+            (None, _) => Ok(ReplayMode::Live),
+            _ => anyhow::bail!("Usage: [--record <path> | --replay <path> | --verify <path>]"),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        match self {
+            ReplayMode::Live => 0,
+            ReplayMode::Record { replay, .. }
+            | ReplayMode::Playback(replay)
+            | ReplayMode::Verify(replay) => replay.seed,
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self, ReplayMode::Verify(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self, ReplayMode::Playback(_) | ReplayMode::Verify(_))
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// 64-bit FNV-1a. Unlike DefaultHasher its output is fixed, so a hash recorded by one build can be
+// checked by another.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Entity ids depend on the order startup systems happen to run in, so each body is hashed on its
+// own and the sorted body hashes are combined.
+pub fn world_hash(
+    query: &Query<(
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        Option<&Health>,
+    )>,
+) -> u64 {
+    let mut body_hashes = query
+        .iter()
+        .map(|(position, velocity, health)| {
+            let mut hasher = Fnv1a::new();
+            let translation = position.0.position.translation;
+            hasher.write_f32(translation.x);
+            hasher.write_f32(translation.y);
+            hasher.write_f32(position.0.position.rotation.angle());
+            hasher.write_f32(velocity.linvel.x);
+            hasher.write_f32(velocity.linvel.y);
+            if let Some(health) = health {
+                hasher.write(&health.current_health.to_le_bytes());
+            }
+            hasher.finish()
+        })
+        .collect::<Vec<_>>();
+    body_hashes.sort_unstable();
+    let mut hasher = Fnv1a::new();
+    for body_hash in body_hashes {
+        hasher.write(&body_hash.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+pub fn replay_playback_system(
+    fixed_time: Res<FixedTime>,
+    replay_mode: Res<ReplayMode>,
+    mut player_query: Query<&mut PlayerInputState>,
+) {
+    if !fixed_time.running() {
+        return;
+    }
+    if let ReplayMode::Playback(replay) | ReplayMode::Verify(replay) = &*replay_mode {
+        let input = replay
+            .inputs
+            .get(fixed_time.tick() as usize)
+            .cloned()
+            .unwrap_or_default();
+        for mut player_input in player_query.iter_mut() {
+            *player_input = input.clone();
+        }
+    }
+}
+
+pub fn replay_record_system(
+    fixed_time: Res<FixedTime>,
+    mut replay_mode: ResMut<ReplayMode>,
+    player_query: Query<&PlayerInputState>,
+) {
+    if !fixed_time.running() {
+        return;
+    }
+    if let ReplayMode::Record { replay, .. } = &mut *replay_mode {
+        if let Some(input) = player_query.iter().next() {
+            replay.inputs.push(input.clone());
+        }
+    }
+}
+
+pub fn replay_finish_system(
+    fixed_time: Res<FixedTime>,
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    mut exit_events: ResMut<Events<AppExit>>,
+    mut replay_mode: ResMut<ReplayMode>,
+    body_query: Query<(
+        &RigidBodyPositionComponent,
+        &RigidBodyVelocityComponent,
+        Option<&Health>,
+    )>,
+) {
+    if !fixed_time.running() {
+        return;
+    }
+    let exiting = exit_events.get_reader().iter(&exit_events).next().is_some();
+    match &mut *replay_mode {
+        ReplayMode::Record { path, replay } => {
+            let save_requested = keyboard_input
+                .map(|keyboard_input| keyboard_input.just_pressed(KeyCode::F5))
+                .unwrap_or(false);
+            if exiting || save_requested {
+                replay.final_hash = Some(world_hash(&body_query));
+                match replay.save(path) {
+                    Ok(()) => println!("Saved {} ticks to {}", replay.inputs.len(), path),
+                    Err(err) => println!("Could not save replay to {}: {}", path, err),
+                }
+            }
+        }
+        ReplayMode::Verify(replay) => {
+            if fixed_time.tick() < replay.inputs.len() as u64 {
+                return;
+            }
+            let hash = world_hash(&body_query);
+            match replay.final_hash {
+                Some(expected) if expected == hash => {
+                    println!(
+                        "Replay verified after {} ticks: {:016x}",
+                        replay.inputs.len(),
+                        hash
+                    );
+                    exit_events.send(AppExit);
+                }
+                Some(expected) => {
+                    println!(
+                        "Replay diverged after {} ticks: expected {:016x}, got {:016x}",
+                        replay.inputs.len(),
+                        expected,
+                        hash
+                    );
+                    std::process::exit(1);
+                }
+                None => {
+                    println!(
+                        "Replay has no final hash to verify against, got {:016x}",
+                        hash
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if app.world.get_resource::<ReplayMode>().is_none() {
+            app.insert_resource(ReplayMode::Live);
+        }
+        let replay_mode = app.world.get_resource::<ReplayMode>().unwrap();
+        let seed = replay_mode.seed();
+        if replay_mode.is_headless() {
+            app.world
+                .get_resource_mut::<FixedTime>()
+                .unwrap()
+                .set_lockstep(true);
+        }
+        // Playback and verify reseed from the recording.
+        app.insert_resource(GameRng::new(seed))
+            .add_gameplay_system(replay_playback_system.label(GameplayLabel::Input))
+            .add_gameplay_system(replay_record_system.after(GameplayLabel::Input))
+            .add_system_to_stage(CoreStage::Last, replay_finish_system);
+    }
+}
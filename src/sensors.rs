@@ -1,20 +1,90 @@
 use crate::{
-    animation::hitboxes::{Hitbox, SpriteSheetHitboxes},
+    animation::hitboxes::{Hitbox, HitboxShape, HitboxType, SpriteSheetHitboxes},
     prelude::*,
 };
 
-#[derive(Debug)]
 pub struct SensorEntity {
     pub center: Vec2,
-    pub half_x: f32,
-    pub half_y: f32,
+    pub angle: f32,
+    pub shape: ColliderShape,
+}
+
+// Hitboxes are in pixels with y pointing down, sensors are in physics units with y pointing up.
+fn to_physics(pixel: Vec2, scale: f32) -> Vec2 {
+    Vec2::new(pixel.x, -pixel.y) / scale
 }
 
 impl SensorEntity {
+    pub fn cuboid(center: Vec2, half_x: f32, half_y: f32) -> Self {
+        SensorEntity {
+            center,
+            angle: 0.0,
+            shape: ColliderShape::cuboid(half_x, half_y),
+        }
+    }
+
+    pub fn from_hitbox(hitbox: &Hitbox, scale: f32) -> Self {
+        let rect = || {
+            SensorEntity::cuboid(
+                hitbox.get_center() / scale,
+                hitbox.get_half_x() / scale,
+                hitbox.get_half_y() / scale,
+            )
+        };
+        match &hitbox.shape {
+            HitboxShape::Rect => rect(),
+            HitboxShape::Circle { center, radius } => SensorEntity {
+                center: to_physics(*center, scale),
+                angle: 0.0,
+                shape: ColliderShape::ball(radius / scale),
+            },
+            HitboxShape::Capsule { a, b, radius } => {
+                let a = to_physics(*a, scale);
+                let b = to_physics(*b, scale);
+                let center = (a + b) / 2.0;
+                SensorEntity {
+                    center,
+                    angle: 0.0,
+                    shape: ColliderShape::capsule(
+                        point![a.x - center.x, a.y - center.y],
+                        point![b.x - center.x, b.y - center.y],
+                        radius / scale,
+                    ),
+                }
+            }
+            HitboxShape::RotatedRect {
+                center,
+                half_extents,
+                angle,
+            } => SensorEntity {
+                center: to_physics(*center, scale),
+                angle: -angle,
+                shape: ColliderShape::cuboid(half_extents.x / scale, half_extents.y / scale),
+            },
+            HitboxShape::ConvexPolygon(points) => {
+                let (min, max) = hitbox.bounds();
+                let center = to_physics((min + max) / 2.0, scale);
+                let points = points
+                    .iter()
+                    .map(|pixel| to_physics(*pixel, scale) - center)
+                    .map(|local| point![local.x, local.y])
+                    .collect::<Vec<_>>();
+                match ColliderShape::convex_hull(&points) {
+                    Some(shape) => SensorEntity {
+                        center,
+                        angle: 0.0,
+                        shape,
+                    },
+                    None => rect(),
+                }
+            }
+        }
+    }
+
     pub fn collider_bundle(&self) -> ColliderBundle {
         ColliderBundle {
             collider_type: ColliderType::Sensor.into(),
-            shape: ColliderShape::cuboid(self.half_x, self.half_y).into(),
+            shape: self.shape.clone().into(),
             mass_properties: MassProperties::new(point![0.0, 0.0], 0.0, 0.0).into(),
             material: ColliderMaterial::new(0.0, 0.0).into(),
             flags: ActiveEvents::INTERSECTION_EVENTS.into(),
@@ -25,68 +95,231 @@ impl SensorEntity {
     pub fn collider_parent(&self, handle: RigidBodyHandle) -> ColliderParentComponent {
         ColliderParent {
             handle,
-            pos_wrt_parent: self.center.into(),
+            pos_wrt_parent: Isometry::new(vector![self.center.x, self.center.y], self.angle),
         }
         .into()
     }
 }
 
+// Where unused slots are parked, relative to their parent. Parking has to move the sensor out of
+// range: rapier only emits a stopped intersection when the broad phase drops the pair, so a sensor
+// that just stopped testing its groups would keep its old overlaps and never report them again.
+const PARKED_OFFSET: f32 = -10_000.0;
+
+// Marks every pooled sensor child, whether or not it currently carries a Hitbox.
+#[derive(Component, Debug, Default)]
+pub struct HitboxSensor;
+
+// The sensor children of an entity's hitboxes. Slots are reused when the frame changes, so only a
+// frame with more hitboxes of a type than the entity has used before spawns anything.
+#[derive(Component, Debug, Default)]
+pub struct HitboxSensorPool {
+    slots: Vec<(HitboxType, Entity)>,
+    synced: Option<(usize, bool)>,
+}
+
+impl HitboxSensorPool {
+    pub fn slots(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.slots.iter().map(|(_hitbox_type, entity)| *entity)
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn sync_hitboxes(
-    parent_query: Query<(
+    mut commands: Commands,
+    configuration: Res<RapierConfiguration>,
+    mut parent_query: Query<(
         Entity,
-        Option<&Children>,
         &TextureAtlasSprite,
         &SpriteSheetHitboxes,
+        ChangeTrackers<SpriteSheetHitboxes>,
+        Option<&mut HitboxSensorPool>,
     )>,
-    hitbox_query: Query<
-        (Entity, &Hitbox),
+    mut sensor_query: Query<
         (
-            With<ColliderPositionComponent>,
-            With<ColliderShapeComponent>,
+            &mut ColliderShapeComponent,
+            &mut ColliderParentComponent,
+            &mut ColliderFlagsComponent,
         ),
+        With<HitboxSensor>,
     >,
-    configuration: Res<RapierConfiguration>,
-    mut commands: Commands,
 ) {
-    for (parent, children, texture_atlas_sprite, hitboxes) in parent_query.iter() {
-        if let Some(hitboxes) = hitboxes.get_hitboxes(texture_atlas_sprite.index) {
-            for current_hitbox in hitboxes {
-                let mut flipped_hitbox = current_hitbox.clone();
-                if texture_atlas_sprite.flip_x {
-                    flipped_hitbox.flip_x();
-                }
-                if !has_child(&children, |child| {
-                    if let Ok((_entity, hitbox)) = hitbox_query.get(**child) {
-                        flipped_hitbox == *hitbox
-                    } else {
-                        false
+    for (parent, texture_atlas_sprite, hitboxes, hitboxes_tracker, pool) in parent_query.iter_mut()
+    {
+        let frame = (texture_atlas_sprite.index, texture_atlas_sprite.flip_x);
+        let mut new_pool = None;
+        let pool = match pool {
+            Some(pool) => pool.into_inner(),
+            None => new_pool.insert(HitboxSensorPool::default()),
+        };
+        if pool.synced == Some(frame) && !hitboxes_tracker.is_changed() {
+            continue;
+        }
+        pool.synced = Some(frame);
+        let mut used = vec![false; pool.slots.len()];
+        for current_hitbox in hitboxes.get_hitboxes(frame.0).into_iter().flatten() {
+            let mut flipped_hitbox = current_hitbox.clone();
+            if texture_atlas_sprite.flip_x {
+                flipped_hitbox.flip_x();
+            }
+            let sensor_entity = SensorEntity::from_hitbox(&flipped_hitbox, configuration.scale);
+            let free_slot =
+                pool.slots
+                    .iter()
+                    .enumerate()
+                    .position(|(slot, (hitbox_type, _entity))| {
+                        !used[slot] && *hitbox_type == flipped_hitbox.hitbox_type
+                    });
+            match free_slot {
+                Some(slot) => {
+                    used[slot] = true;
+                    let entity = pool.slots[slot].1;
+                    if let Ok((mut shape, mut collider_parent, mut flags)) =
+                        sensor_query.get_mut(entity)
+                    {
+                        *shape = sensor_entity.shape.clone().into();
+                        *collider_parent = sensor_entity.collider_parent(parent.handle());
+                        flags.collision_groups = InteractionGroups::all();
                     }
-                }) {
-                    let sensor_entity = SensorEntity {
-                        center: flipped_hitbox.get_center() / configuration.scale,
-                        half_x: flipped_hitbox.get_half_x() / configuration.scale,
-                        half_y: flipped_hitbox.get_half_y() / configuration.scale,
-                    };
+                    commands.entity(entity).insert(flipped_hitbox);
+                }
+                None => {
                     let child = commands
                         .spawn_bundle(sensor_entity.collider_bundle())
                         .insert(sensor_entity.collider_parent(parent.handle()))
                         .insert(ColliderDebugRender::default())
-                        .insert(flipped_hitbox)
                         .insert(ColliderPositionSync::Discrete)
+                        .insert(HitboxSensor)
+                        .insert(flipped_hitbox.clone())
                         .id();
                     commands.entity(parent).add_child(child);
+                    pool.slots.push((flipped_hitbox.hitbox_type, child));
+                    used.push(true);
                 }
             }
-            if let Some(children) = children {
-                children
-                    .iter()
-                    .filter_map(|child| hitbox_query.get(*child).ok())
-                    .for_each(|(entity, hitbox)| {
-                        if !hitboxes.contains(hitbox) {
-                            commands.entity(entity).despawn();
-                        }
-                    });
+        }
+        // Unused slots stay parented but are moved out of range until a later frame needs them.
+        for (slot, (_hitbox_type, entity)) in pool.slots.iter().enumerate() {
+            if !used[slot] {
+                if let Ok((_shape, mut collider_parent, mut flags)) = sensor_query.get_mut(*entity)
+                {
+                    collider_parent.pos_wrt_parent = Isometry::translation(0.0, PARKED_OFFSET);
+                    flags.collision_groups = InteractionGroups::none();
+                }
+                commands.entity(*entity).remove::<Hitbox>();
+            }
+        }
+        if let Some(new_pool) = new_pool {
+            commands.entity(parent).insert(new_pool);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::Events;
+
+    use super::*;
+
+    const SLASH_HITBOXES: &str = "(hitboxes:[[],\
+        [(hitbox_type:Hit,min:(-2,-46),max:(62,14))],\
+        [(hitbox_type:Hit,min:(18,-12),max:(64,38))],\
+        [(hitbox_type:Hit,min:(24,14),max:(64,32)),(hitbox_type:Hurt,min:(-16,-16),max:(16,16))]])";
+
+    const SWING_HITBOXES: &str = "(hitboxes:[[],[(hitbox_type:Hit,min:(0,-8),max:(32,8))]])";
+
+    // Plays a looping slash, turning around every loop, and compares the sensors the pool spawns
+    // with what respawning on every frame change would cost.
+    #[test]
+    fn test_attack_animation_sensor_spawns() {
+        let hitboxes: SpriteSheetHitboxes = ron::de::from_str(SLASH_HITBOXES).unwrap();
+        let mut world = World::new();
+        world.insert_resource(RapierConfiguration::default());
+        let entity = world
+            .spawn()
+            .insert(TextureAtlasSprite::default())
+            .insert(hitboxes.clone())
+            .id();
+        let mut stage = SystemStage::single(sync_hitboxes);
+        let mut respawned = 0;
+        let mut last_frame = None;
+        for tick in 0..600 {
+            let frame = (tick / 6 % 4, tick / 24 % 2 == 1);
+            if last_frame != Some(frame) {
+                respawned += hitboxes
+                    .get_hitboxes(frame.0)
+                    .map_or(0, |frame| frame.len());
+                last_frame = Some(frame);
+            }
+            let mut sprite = world.get_mut::<TextureAtlasSprite>(entity).unwrap();
+            sprite.index = frame.0;
+            sprite.flip_x = frame.1;
+            stage.run(&mut world);
+        }
+        let pooled = world
+            .query_filtered::<Entity, With<HitboxSensor>>()
+            .iter(&world)
+            .count();
+        assert_eq!(pooled, 2);
+        assert_eq!(respawned, 100);
+    }
+
+    // Swings twice at a target that never moves, so the second swing reuses the parked sensor
+    // while the target is still where the first one hit it.
+    #[test]
+    fn test_repeated_swings_hit_a_target_in_range() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .insert_resource(RapierConfiguration {
+                gravity: Vector::zeros(),
+                timestep_mode: bevy_rapier2d::physics::TimestepMode::FixedTimestep,
+                ..Default::default()
+            })
+            .add_system_to_stage(CoreStage::PreUpdate, sync_hitboxes);
+        let attacker = app
+            .world
+            .spawn()
+            .insert_bundle(RigidBodyBundle {
+                body_type: RigidBodyType::Static.into(),
+                ..Default::default()
+            })
+            .insert(TextureAtlasSprite::default())
+            .insert(ron::de::from_str::<SpriteSheetHitboxes>(SWING_HITBOXES).unwrap())
+            .id();
+        let target = app
+            .world
+            .spawn()
+            .insert_bundle(RigidBodyBundle {
+                position: [16.0, 0.0].into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: ColliderShape::cuboid(4.0, 4.0).into(),
+                ..Default::default()
+            })
+            .id();
+        for frame in [1, 0, 1, 0] {
+            app.world
+                .get_mut::<TextureAtlasSprite>(attacker)
+                .unwrap()
+                .index = frame;
+            for _ in 0..5 {
+                app.update();
             }
         }
+        let events = app
+            .world
+            .get_resource::<Events<IntersectionEvent>>()
+            .unwrap();
+        let target_events = events
+            .get_reader()
+            .iter(events)
+            .filter(|event| {
+                event.collider1.entity() == target || event.collider2.entity() == target
+            })
+            .map(|event| event.intersecting)
+            .collect::<Vec<_>>();
+        assert_eq!(target_events, vec![true, false, true, false]);
     }
 }
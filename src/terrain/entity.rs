@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{layers::CollisionLayer, prelude::*};
 
 #[derive(Component, Debug, Reflect, Inspectable, Default, Copy, Clone)]
 pub struct TerrainBlock;
@@ -10,7 +10,13 @@ pub fn terrain_collider_bundle(width: f32, height: f32) -> ColliderBundle {
     ColliderBundle {
         shape: ColliderShapeComponent(ColliderShape::cuboid(width / 2.0, height / 2.0)),
         material: ColliderMaterial::new(1.0, 0.0).into(),
-        flags: (ActiveEvents::CONTACT_EVENTS | ActiveEvents::INTERSECTION_EVENTS).into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::CONTACT_EVENTS | ActiveEvents::INTERSECTION_EVENTS,
+            collision_groups: CollisionLayer::Terrain.collision_groups(),
+            solver_groups: CollisionLayer::Terrain.solver_groups(),
+            ..Default::default()
+        }
+        .into(),
         ..Default::default()
     }
 }
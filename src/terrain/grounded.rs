@@ -1,5 +1,5 @@
 use super::TerrainBlock;
-use crate::prelude::*;
+use crate::{prelude::*, timestep::FixedTime};
 
 #[derive(Default, Component, Debug, Clone, Reflect, Inspectable)]
 #[reflect(Component)]
@@ -31,19 +31,20 @@ impl GroundedState {
     }
 }
 
+#[allow(clippy::clone_on_copy)]
 pub fn grounded_system(
     query_pipeline: Res<QueryPipeline>,
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     collider_query: QueryPipelineColliderComponentsQuery,
     mut player_query: Query<(&RigidBodyPositionComponent, &mut GroundedState)>,
     terrain_query: Query<&TerrainBlock>,
 ) {
     for (position, mut grounded) in player_query.iter_mut() {
         if grounded.air_for > 0.0 {
-            grounded.air_for -= time.delta_seconds();
+            grounded.air_for -= fixed_time.delta_seconds();
             continue;
         }
-        grounded.grounded_for -= time.delta_seconds();
+        grounded.grounded_for -= fixed_time.delta_seconds();
         let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
         let shape = Cuboid::new(Vec2::new(grounded.half_width - 0.1, grounded.half_height).into());
         let mut shape_pos = position.0.position.translation.clone();
@@ -1,10 +1,15 @@
 mod entity;
 mod grounded;
+mod walls;
 
 pub use self::entity::*;
 pub use self::grounded::*;
+pub use self::walls::*;
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    timestep::{GameplayAppExt, GameplayLabel},
+};
 
 pub fn spawn_parallax(mut commands: Commands, assets: Res<AssetServer>) {
     let mut transform = Transform::default();
@@ -45,7 +50,18 @@ impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(spawn_bedrock)
             .add_startup_system(spawn_parallax)
-            .add_system(grounded_system)
+            .add_gameplay_system(
+                grounded_system
+                    .label(GameplayLabel::Environment)
+                    .after(GameplayLabel::Input),
+            )
+            .add_gameplay_system(
+                wall_contact_system
+                    .label(GameplayLabel::Environment)
+                    .after(GameplayLabel::Input),
+            )
+            .register_type::<WallContactState>()
+            .register_inspectable::<WallContactState>()
             .register_type::<TerrainBlock>()
             .register_inspectable::<TerrainBlock>();
     }
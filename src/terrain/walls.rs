@@ -0,0 +1,66 @@
+use super::TerrainBlock;
+use crate::prelude::*;
+
+#[derive(Default, Component, Debug, Clone, Reflect, Inspectable)]
+#[reflect(Component)]
+pub struct WallContactState {
+    wall_dir: f32,
+    half_width: f32,
+    half_height: f32,
+    reach: f32,
+}
+
+impl WallContactState {
+    pub fn new(half_width: f32, half_height: f32, reach: f32) -> Self {
+        WallContactState {
+            wall_dir: 0.0,
+            half_width,
+            half_height,
+            reach,
+        }
+    }
+
+    // -1.0 for a wall on the left, 1.0 for a wall on the right, 0.0 for no wall.
+    pub fn wall_dir(&self) -> f32 {
+        self.wall_dir
+    }
+
+    pub fn touching_wall(&self) -> bool {
+        self.wall_dir != 0.0
+    }
+}
+
+pub fn wall_contact_system(
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    mut wall_query: Query<(&RigidBodyPositionComponent, &mut WallContactState)>,
+    terrain_query: Query<&TerrainBlock>,
+) {
+    for (position, mut wall_contact) in wall_query.iter_mut() {
+        wall_contact.wall_dir = 0.0;
+        let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
+        let shape =
+            Cuboid::new(Vec2::new(wall_contact.reach / 2.0, wall_contact.half_height - 0.1).into());
+        for side in [-1.0, 1.0] {
+            let mut shape_pos = position.0.position.translation;
+            shape_pos.x += side * (wall_contact.half_width + wall_contact.reach / 2.0);
+            let groups = InteractionGroups::all();
+            let filter = None;
+            query_pipeline.intersections_with_shape(
+                &collider_set,
+                &shape_pos.into(),
+                &shape,
+                groups,
+                filter,
+                |handle| {
+                    if terrain_query.get(handle.entity()).is_ok() {
+                        wall_contact.wall_dir = side;
+                        false
+                    } else {
+                        true
+                    }
+                },
+            );
+        }
+    }
+}
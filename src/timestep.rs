@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+use bevy::{
+    app::Events,
+    asset::LoadState,
+    ecs::{
+        schedule::{IntoSystemDescriptor, ShouldRun},
+        system::Resource,
+    },
+};
+use bevy_rapier2d::physics::{self, PhysicsSystems};
+
+use crate::{
+    animation::{assets::SpriteAnimationAsset, component_types::ParameterizedSpriteAnimationSet},
+    prelude::*,
+};
+
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+// Gameplay runs in this order every tick, so a replayed run sees the same state as the recording.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameplayLabel {
+    Input,
+    Environment,
+    Sense,
+    Decide,
+    Act,
+    Resolve,
+    Damage,
+    Animate,
+    Sample,
+}
+
+// Gameplay runs in its own schedule after CoreStage::Update. FixedUpdate runs the stages below
+// once per tick, as many times as the frame's real time allows, so a tick means the same thing
+// however fast the game renders.
+#[derive(StageLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameplayStage {
+    FixedUpdate,
+    // Gameplay events are flipped and bodies spawned last tick are handed to physics.
+    PreTick,
+    AttachColliders,
+    Tick,
+    // Physics steps once everything the tick spawned or despawned has been applied.
+    Physics,
+}
+
+// Ticks run in one frame before the clock gives up on catching up, so a long hitch slows the game
+// down instead of freezing it.
+pub const MAX_TICKS_PER_FRAME: u32 = 4;
+
+pub const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+
+// Slows down how quickly real time turns into ticks. Meant for debugging, so it isn't recorded in
+// replays.
+#[derive(Debug)]
+pub struct TimeScale {
+    pub scale: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale { scale: 1.0 }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FixedTime {
+    tick: u64,
+    running: bool,
+    accumulated: f32,
+    // Runs exactly one tick per frame regardless of real time, for headless replay verification.
+    lockstep: bool,
+}
+
+// Freezes one entity's clocks for the few frames of impact after a hit lands.
+#[derive(Component, Debug, Reflect, Inspectable, Default, Clone)]
+pub struct HitStop {
+    pub remaining: f32,
+    pub held_velocity: Vec2,
+    pub held_gravity_scale: f32,
+}
+
+impl FixedTime {
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    pub fn set_lockstep(&mut self, lockstep: bool) {
+        self.lockstep = lockstep;
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        if self.running {
+            FIXED_TIMESTEP
+        } else {
+            0.0
+        }
+    }
+
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.delta_seconds())
+    }
+
+    // Like delta_seconds, but zero while the entity is in hit-stop.
+    pub fn entity_delta_seconds(&self, hit_stop: Option<&HitStop>) -> f32 {
+        if hit_stop.is_some() {
+            0.0
+        } else {
+            self.delta_seconds()
+        }
+    }
+
+    pub fn entity_delta(&self, hit_stop: Option<&HitStop>) -> Duration {
+        Duration::from_secs_f32(self.entity_delta_seconds(hit_stop))
+    }
+}
+
+// Every random roll gameplay makes has to come from here, so a replay's seed reproduces it.
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: Pcg32,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+        }
+    }
+}
+
+// Data assets that gameplay reads from, which have to be loaded before the clock starts.
+#[derive(Debug, Default)]
+pub struct PreloadedAssets {
+    handles: Vec<HandleUntyped>,
+}
+
+impl PreloadedAssets {
+    pub fn add(&mut self, handle: HandleUntyped) {
+        self.handles.push(handle);
+    }
+
+    pub fn loaded(&self, asset_server: &AssetServer) -> bool {
+        self.handles.is_empty()
+            || asset_server.get_group_load_state(self.handles.iter().map(|handle| handle.id))
+                == LoadState::Loaded
+    }
+}
+
+// The clock only starts once every sprite has been unpacked, so asset loading times don't leak
+// into the simulation.
+#[allow(clippy::too_many_arguments)]
+pub fn fixed_time_system(
+    mut fixed_time: ResMut<FixedTime>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut configuration: ResMut<RapierConfiguration>,
+    asset_server: Res<AssetServer>,
+    preloaded: Res<PreloadedAssets>,
+    loading_query: Query<
+        (),
+        (
+            With<Handle<SpriteAnimationAsset>>,
+            Without<ParameterizedSpriteAnimationSet>,
+        ),
+    >,
+    loaded_query: Query<(), With<ParameterizedSpriteAnimationSet>>,
+) {
+    if fixed_time.running {
+        if fixed_time.lockstep {
+            fixed_time.accumulated = FIXED_TIMESTEP;
+        } else {
+            fixed_time.accumulated = (fixed_time.accumulated
+                + time.delta_seconds() * time_scale.scale)
+                .min(FIXED_TIMESTEP * MAX_TICKS_PER_FRAME as f32);
+        }
+    } else if loading_query.iter().next().is_none()
+        && loaded_query.iter().next().is_some()
+        && preloaded.loaded(&asset_server)
+    {
+        fixed_time.running = true;
+        fixed_time.accumulated = FIXED_TIMESTEP;
+        configuration.query_pipeline_active = true;
+    }
+}
+
+// Run criteria of GameplayStage::FixedUpdate, spending the accumulated time one tick at a time.
+pub fn fixed_tick_run_criteria(mut fixed_time: ResMut<FixedTime>) -> ShouldRun {
+    if fixed_time.running && fixed_time.accumulated >= FIXED_TIMESTEP {
+        fixed_time.accumulated -= FIXED_TIMESTEP;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+// The physics plugin also steps the world once per frame in CoreStage::Update. The pipeline is
+// only switched on around the step inside the tick, so that one never advances anything.
+fn physics_step_begin_system(mut configuration: ResMut<RapierConfiguration>) {
+    configuration.physics_pipeline_active = true;
+}
+
+fn physics_step_end_system(
+    mut configuration: ResMut<RapierConfiguration>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    configuration.physics_pipeline_active = false;
+    fixed_time.tick += 1;
+}
+
+pub trait GameplayAppExt {
+    // Adds a system to GameplayStage::Tick, where the GameplayLabel ordering applies.
+    fn add_gameplay_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+
+    // Like add_event, but the buffers flip every tick instead of every frame, so events sent by
+    // one tick are seen by the next however many ticks a frame runs.
+    fn add_gameplay_event<T: Resource>(&mut self) -> &mut Self;
+}
+
+impl GameplayAppExt for App {
+    fn add_gameplay_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.stage(GameplayStage::FixedUpdate, |schedule: &mut Schedule| {
+            schedule.add_system_to_stage(GameplayStage::Tick, system)
+        })
+    }
+
+    fn add_gameplay_event<T: Resource>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>().stage(
+            GameplayStage::FixedUpdate,
+            |schedule: &mut Schedule| {
+                schedule.add_system_to_stage(GameplayStage::PreTick, Events::<T>::update_system)
+            },
+        )
+    }
+}
+
+pub fn time_scale_debug_system(keys: Res<Input<KeyCode>>, mut time_scale: ResMut<TimeScale>) {
+    if keys.just_pressed(KeyCode::F2) {
+        time_scale.scale = (time_scale.scale / 2.0).max(MIN_TIME_SCALE);
+    }
+    if keys.just_pressed(KeyCode::F3) {
+        time_scale.scale = 1.0;
+    }
+}
+
+pub struct TimestepPlugin;
+
+impl Plugin for TimestepPlugin {
+    fn build(&self, app: &mut App) {
+        let fixed_update = Schedule::default()
+            .with_run_criteria(fixed_tick_run_criteria.system())
+            .with_stage(GameplayStage::PreTick, SystemStage::parallel())
+            .with_stage(GameplayStage::AttachColliders, SystemStage::parallel())
+            .with_stage(GameplayStage::Tick, SystemStage::parallel())
+            .with_stage(GameplayStage::Physics, SystemStage::parallel())
+            .with_system_in_stage(
+                GameplayStage::PreTick,
+                physics::attach_bodies_and_colliders_system,
+            )
+            .with_system_in_stage(GameplayStage::PreTick, physics::create_joints_system)
+            .with_system_in_stage(
+                GameplayStage::AttachColliders,
+                physics::finalize_collider_attach_to_bodies,
+            )
+            .with_system_in_stage(
+                GameplayStage::Physics,
+                physics_step_begin_system.before(PhysicsSystems::StepWorld),
+            )
+            .with_system_in_stage(
+                GameplayStage::Physics,
+                physics::step_world_system::<NoUserData>.label(PhysicsSystems::StepWorld),
+            )
+            .with_system_in_stage(
+                GameplayStage::Physics,
+                physics_step_end_system.after(PhysicsSystems::StepWorld),
+            );
+        app.init_resource::<FixedTime>()
+            .init_resource::<TimeScale>()
+            .init_resource::<PreloadedAssets>()
+            .insert_resource(IntegrationParameters {
+                dt: FIXED_TIMESTEP,
+                ..Default::default()
+            })
+            .add_stage_after(CoreStage::Update, GameplayStage::FixedUpdate, fixed_update)
+            .add_gameplay_event::<IntersectionEvent>()
+            .add_gameplay_event::<ContactEvent>()
+            .add_system_to_stage(CoreStage::First, fixed_time_system)
+            .add_system(time_scale_debug_system)
+            .register_type::<HitStop>()
+            .register_inspectable::<HitStop>();
+    }
+}